@@ -1,24 +1,120 @@
 // Advent of Code 2022: Day 2 Rock Paper Scissors
 // https://adventofcode.com/2022/day/2
 
+use std::cmp::Ordering;
+use std::env;
+use std::fmt;
 use std::fs;
+use std::process;
 
 fn main() {
     // Read a file:
     let tournament = fs::read_to_string("../../inputs/day_2/example1.txt").unwrap();
     // Will panic, file opening error unhandled
 
-    let mut total_score = 0;
-
-    tournament.lines().for_each(|round| {
-        let hand_inputs: Vec<&str> = round.split_whitespace().collect();
-        let opponent_hand_input = hand_inputs[0];
-        let my_hand_input = hand_inputs[1];
+    let guide = match parse_guide(&tournament) {
+        Ok(guide) => guide,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
 
-        total_score += round_score(opponent_hand_input, my_hand_input);
-    });
+    let total_score: i32 = guide
+        .iter()
+        .map(|(opponent_hand_input, my_hand_input)| round_score(opponent_hand_input, my_hand_input))
+        .sum();
 
     println!("Total Score: {}", total_score);
+
+    // X/Y/Z could mean either the hand to play or the result to aim for.
+    let hand_report = solve_mappings(&guide, &ColumnMapping::all_hands());
+    let outcome_report = solve_mappings(&guide, &ColumnMapping::all_outcomes());
+
+    for report in [hand_report, outcome_report].iter().flatten() {
+        println!(
+            "Best: {} {:?}, Worst: {} {:?}",
+            report.best.1, report.best.0, report.worst.1, report.worst.0
+        );
+    }
+
+    // Any strategy guide paths passed as args play each other in a round robin tournament
+    let guide_paths: Vec<String> = env::args().skip(1).collect();
+
+    if guide_paths.len() >= 2 {
+        let players: Vec<Player> = guide_paths
+            .iter()
+            .map(|path| {
+                let guide = match fs::read_to_string(path) {
+                    Ok(guide) => guide,
+                    Err(e) => {
+                        eprintln!("Cannot read strategy guide {}: {}", path, e);
+                        process::exit(1);
+                    }
+                };
+
+                match Player::new(path, &guide) {
+                    Ok(player) => player,
+                    Err(e) => {
+                        eprintln!("{}: {}", path, e);
+                        process::exit(1);
+                    }
+                }
+            })
+            .collect();
+
+        println!("Player | W | D | L | Rounds +/- | Points");
+
+        for standing in round_robin(&players) {
+            println!(
+                "{} | {} | {} | {} | {} | {}",
+                players[standing.player].name,
+                standing.wins,
+                standing.draws,
+                standing.losses,
+                standing.round_difference(),
+                standing.points()
+            );
+        }
+    }
+}
+
+// A round of the strategy guide that isn't exactly two hand inputs
+#[derive(Debug, PartialEq)]
+struct ParseGuideError {
+    line: usize,
+    round: String,
+}
+
+impl fmt::Display for ParseGuideError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Line {}: expected two hand inputs, found \"{}\"",
+            self.line, self.round
+        )
+    }
+}
+
+// Every round of the strategy guide as (opponent input, my column input).
+// Blank lines are skipped.
+fn parse_guide(tournament: &str) -> std::result::Result<Vec<(&str, &str)>, ParseGuideError> {
+    tournament
+        .lines()
+        .enumerate()
+        .filter(|(_, round)| !round.trim().is_empty())
+        .map(|(i, round)| {
+            let hand_inputs: Vec<&str> = round.split_whitespace().collect();
+
+            match hand_inputs[..] {
+                [opponent_input, my_input] => Ok((opponent_input, my_input)),
+                _ => Err(ParseGuideError {
+                    line: i + 1,
+                    round: round.to_string(),
+                }),
+            }
+        })
+        .collect()
 }
 
 fn round_score(opponent_input: &str, my_input: &str) -> i32 {
@@ -30,7 +126,207 @@ fn round_score(opponent_input: &str, my_input: &str) -> i32 {
     my_hand.score() + result.score()
 }
 
-#[derive(Debug, PartialEq)]
+// One possible meaning of the X, Y, Z column, in that order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnMapping {
+    Hands([Hand; 3]),
+    Outcomes([Result; 3]),
+}
+
+impl ColumnMapping {
+    // Every bijection of X/Y/Z onto hands
+    fn all_hands() -> Vec<ColumnMapping> {
+        permutations(HANDS)
+            .into_iter()
+            .map(ColumnMapping::Hands)
+            .collect()
+    }
+
+    // Every bijection of X/Y/Z onto round results
+    fn all_outcomes() -> Vec<ColumnMapping> {
+        permutations(RESULTS)
+            .into_iter()
+            .map(ColumnMapping::Outcomes)
+            .collect()
+    }
+
+    // Translate my column input into the hand input that round_score understands.
+    // Anything other than X/Y/Z is passed through untouched.
+    fn translate<'a>(&self, opponent_input: &str, my_input: &'a str) -> &'a str {
+        let column = match my_input {
+            "X" => 0,
+            "Y" => 1,
+            "Z" => 2,
+            _ => return my_input,
+        };
+
+        match self {
+            ColumnMapping::Hands(hands) => hands[column].input(),
+            ColumnMapping::Outcomes(results) => {
+                Hand::for_result(Hand::new(opponent_input), results[column]).input()
+            }
+        }
+    }
+
+    fn guide_score(&self, guide: &[(&str, &str)]) -> i32 {
+        guide
+            .iter()
+            .map(|(opponent_input, my_input)| {
+                round_score(opponent_input, self.translate(opponent_input, my_input))
+            })
+            .sum()
+    }
+}
+
+struct MappingReport {
+    best: (ColumnMapping, i32),
+    worst: (ColumnMapping, i32),
+}
+
+// Score the whole guide under every mapping.
+// Ties keep the mapping that was enumerated first.
+fn solve_mappings(guide: &[(&str, &str)], mappings: &[ColumnMapping]) -> Option<MappingReport> {
+    let mut scores = mappings
+        .iter()
+        .map(|mapping| (*mapping, mapping.guide_score(guide)));
+
+    let first = scores.next()?;
+
+    let mut report = MappingReport {
+        best: first,
+        worst: first,
+    };
+
+    for score in scores {
+        if score.1 > report.best.1 {
+            report.best = score;
+        }
+
+        if score.1 < report.worst.1 {
+            report.worst = score;
+        }
+    }
+
+    Some(report)
+}
+
+fn permutations<T: Copy>(items: [T; 3]) -> Vec<[T; 3]> {
+    let [a, b, c] = items;
+
+    vec![
+        [a, b, c],
+        [a, c, b],
+        [b, a, c],
+        [b, c, a],
+        [c, a, b],
+        [c, b, a],
+    ]
+}
+
+// A tournament player plays the hands in their own column of their strategy guide
+struct Player {
+    name: String,
+    hands: Vec<Hand>,
+}
+
+impl Player {
+    fn new(name: &str, guide: &str) -> std::result::Result<Player, ParseGuideError> {
+        Ok(Player {
+            name: name.to_string(),
+            hands: parse_guide(guide)?
+                .into_iter()
+                .map(|(_, my_input)| Hand::new(my_input))
+                .collect(),
+        })
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Standing {
+    player: usize,
+    wins: i32,
+    draws: i32,
+    losses: i32,
+    rounds_won: i32,
+    rounds_lost: i32,
+}
+
+impl Standing {
+    fn points(&self) -> i32 {
+        3 * self.wins + self.draws
+    }
+
+    fn round_difference(&self) -> i32 {
+        self.rounds_won - self.rounds_lost
+    }
+
+    fn record(&mut self, result: &Result) {
+        match result {
+            Result::Won => self.wins += 1,
+            Result::Draw => self.draws += 1,
+            Result::Lost => self.losses += 1,
+        }
+    }
+}
+
+// Every pair of players plays one match, round by round, for as many rounds as both guides have.
+// The match goes to whoever won more rounds.
+//
+// The league table is ordered by points, then round difference, then rounds won,
+// and finally by the order the players were given in.
+fn round_robin(players: &[Player]) -> Vec<Standing> {
+    let mut standings: Vec<Standing> = (0..players.len())
+        .map(|player| Standing {
+            player,
+            ..Default::default()
+        })
+        .collect();
+
+    for i in 0..players.len() {
+        for j in i + 1..players.len() {
+            let mut i_rounds_won = 0;
+            let mut j_rounds_won = 0;
+
+            for (i_hand, j_hand) in players[i].hands.iter().zip(players[j].hands.iter()) {
+                match i_hand.compare(*j_hand) {
+                    Result::Won => i_rounds_won += 1,
+                    Result::Lost => j_rounds_won += 1,
+                    Result::Draw => (),
+                }
+            }
+
+            let (i_result, j_result) = match i_rounds_won.cmp(&j_rounds_won) {
+                Ordering::Greater => (Result::Won, Result::Lost),
+                Ordering::Less => (Result::Lost, Result::Won),
+                Ordering::Equal => (Result::Draw, Result::Draw),
+            };
+
+            standings[i].record(&i_result);
+            standings[i].rounds_won += i_rounds_won;
+            standings[i].rounds_lost += j_rounds_won;
+
+            standings[j].record(&j_result);
+            standings[j].rounds_won += j_rounds_won;
+            standings[j].rounds_lost += i_rounds_won;
+        }
+    }
+
+    standings.sort_by(|a, b| {
+        b.points()
+            .cmp(&a.points())
+            .then(b.round_difference().cmp(&a.round_difference()))
+            .then(b.rounds_won.cmp(&a.rounds_won))
+            .then(a.player.cmp(&b.player))
+    });
+
+    standings
+}
+
+const HANDS: [Hand; 3] = [Hand::Rock, Hand::Paper, Hand::Scissors];
+
+const RESULTS: [Result; 3] = [Result::Lost, Result::Draw, Result::Won];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Result {
     Won,
     Draw,
@@ -47,6 +343,7 @@ impl Result {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Hand {
     Rock,
     Paper,
@@ -63,6 +360,23 @@ impl Hand {
         }
     }
 
+    // The opponent's column letter for this hand
+    fn input(&self) -> &'static str {
+        match *self {
+            Hand::Rock => "A",
+            Hand::Paper => "B",
+            Hand::Scissors => "C",
+        }
+    }
+
+    // The hand to play against the opponent to get the wanted result
+    fn for_result(opponent: Hand, result: Result) -> Hand {
+        HANDS
+            .into_iter()
+            .find(|hand| hand.compare(opponent) == result)
+            .unwrap()
+    }
+
     fn score(&self) -> i32 {
         match *self {
             Hand::Rock => 1,
@@ -94,9 +408,17 @@ impl Hand {
 
 #[cfg(test)]
 mod tests {
+    use crate::parse_guide;
+    use crate::round_robin;
+    use crate::solve_mappings;
+    use crate::ColumnMapping;
     use crate::Hand;
+    use crate::ParseGuideError;
+    use crate::Player;
     use crate::Result;
 
+    const EXAMPLE_GUIDE: &str = "A Y\nB X\nC Z";
+
     #[test]
     fn rock_beats_scissors() {
         let rock = Hand::Rock;
@@ -159,4 +481,110 @@ mod tests {
 
         assert_eq!(scissors.compare(Hand::Scissors), Result::Draw);
     }
+
+    #[test]
+    fn hand_for_result() {
+        assert_eq!(Hand::for_result(Hand::Rock, Result::Won), Hand::Paper);
+        assert_eq!(Hand::for_result(Hand::Paper, Result::Lost), Hand::Rock);
+        assert_eq!(
+            Hand::for_result(Hand::Scissors, Result::Draw),
+            Hand::Scissors
+        );
+    }
+
+    #[test]
+    fn example_guide_as_hands() {
+        let guide = parse_guide(EXAMPLE_GUIDE).unwrap();
+        let mapping = ColumnMapping::Hands([Hand::Rock, Hand::Paper, Hand::Scissors]);
+
+        assert_eq!(mapping.guide_score(&guide), 15);
+    }
+
+    #[test]
+    fn example_guide_as_outcomes() {
+        let guide = parse_guide(EXAMPLE_GUIDE).unwrap();
+        let mapping = ColumnMapping::Outcomes([Result::Lost, Result::Draw, Result::Won]);
+
+        assert_eq!(mapping.guide_score(&guide), 12);
+    }
+
+    #[test]
+    fn all_mappings_are_bijections() {
+        assert_eq!(ColumnMapping::all_hands().len(), 6);
+        assert_eq!(ColumnMapping::all_outcomes().len(), 6);
+    }
+
+    #[test]
+    fn example_guide_best_and_worst_hands() {
+        let guide = parse_guide(EXAMPLE_GUIDE).unwrap();
+        let report = solve_mappings(&guide, &ColumnMapping::all_hands()).unwrap();
+
+        // X = Scissors, Y = Paper, Z = Rock wins every round
+        assert_eq!(
+            report.best,
+            (
+                ColumnMapping::Hands([Hand::Scissors, Hand::Paper, Hand::Rock]),
+                24
+            )
+        );
+        assert!(report.worst.1 <= 15);
+    }
+
+    #[test]
+    fn no_mappings_no_report() {
+        let guide = parse_guide(EXAMPLE_GUIDE).unwrap();
+
+        assert!(solve_mappings(&guide, &[]).is_none());
+    }
+
+    #[test]
+    fn parse_guide_rejects_incomplete_rounds() {
+        assert_eq!(parse_guide("A Y\n\nC Z\n").unwrap().len(), 2);
+        assert_eq!(
+            parse_guide("A Y\nB\nC Z"),
+            Err(ParseGuideError {
+                line: 2,
+                round: "B".to_string()
+            })
+        );
+        assert_eq!(
+            parse_guide("A Y Z").unwrap_err().to_string(),
+            "Line 1: expected two hand inputs, found \"A Y Z\""
+        );
+        assert!(Player::new("broken", "A").is_err());
+    }
+
+    #[test]
+    fn round_robin_league_table() {
+        let players = [
+            Player::new("rock", "A X\nA X").unwrap(),
+            Player::new("paper", "A Y\nA Y").unwrap(),
+            Player::new("scissors", "A Z\nA Z").unwrap(),
+            Player::new("mixed", "A Y\nA Z").unwrap(),
+        ];
+
+        let table = round_robin(&players);
+
+        let order: Vec<usize> = table.iter().map(|standing| standing.player).collect();
+        let points: Vec<i32> = table.iter().map(|standing| standing.points()).collect();
+
+        // rock and mixed are level on points and round difference, rock won more rounds
+        assert_eq!(order, vec![2, 0, 3, 1]);
+        assert_eq!(points, vec![6, 4, 4, 3]);
+        assert_eq!((table[3].wins, table[3].draws, table[3].losses), (1, 0, 2));
+    }
+
+    #[test]
+    fn round_robin_tie_break_by_input_order() {
+        let players = [
+            Player::new("first", "A X").unwrap(),
+            Player::new("second", "A X").unwrap(),
+        ];
+
+        let table = round_robin(&players);
+
+        assert_eq!(table[0].player, 0);
+        assert_eq!(table[0].draws, 1);
+        assert_eq!(table[1].player, 1);
+    }
 }
@@ -1,46 +1,573 @@
-use std::{collections::HashSet, fs};
+use advent_of_code_2022::XorShift;
+use std::cmp::Reverse;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Instant;
+use std::{collections::HashSet, env, fs, process};
 
 fn main() {
-    let rucksacks = fs::read_to_string("../../inputs/day_3/example1.txt").unwrap();
+    // day_3 --bench <rucksack count>: compare HashSet and ItemSet on generated rucksacks
+    // day_3 --report: list every shared item instead of only the first one
+    let mut args = env::args().skip(1);
 
-    let mut priorities_sum = 0;
+    let mode = args.next();
+
+    if let Some("--bench") = mode.as_deref() {
+        let rucksack_count = args.next().and_then(|n| n.parse().ok()).unwrap_or(300_000);
+
+        return bench(rucksack_count);
+    }
 
-    rucksacks.lines().for_each(|rucksack| {
-        let num_items = rucksack.chars().count();
+    let input = fs::read_to_string("../../inputs/day_3/example1.txt").unwrap();
 
-        let compartment_1 = &rucksack[0..num_items / 2];
-        let compartment_2 = &rucksack[num_items / 2..];
+    let mut rucksacks: Vec<Rucksack> = Vec::new();
 
-        let duplicate_item = find_duplicate_item(compartment_1.chars(), compartment_2.chars());
+    for (i, rucksack) in input.lines().enumerate() {
+        match rucksack.parse::<Rucksack>() {
+            Ok(rucksack) => rucksacks.push(rucksack),
+            Err(e) => {
+                eprintln!("Invalid rucksack on line {}: {:?}", i + 1, e);
+                process::exit(1);
+            }
+        }
+    }
 
-        priorities_sum += priority_score(duplicate_item);
-    });
+    let mut priorities_sum = 0;
+
+    for (i, rucksack) in rucksacks.iter().enumerate() {
+        let (compartment_1, compartment_2) = rucksack.compartments();
+
+        match find_duplicate_item(compartment_1.chars(), compartment_2.chars()) {
+            // duplicates come out of an ItemSet, so always have a priority
+            Some(duplicate_item) => priorities_sum += priority_score(duplicate_item).unwrap(),
+            None => println!("Rucksack {} has no item in both compartments", i + 1),
+        }
+    }
 
     println!("Priorities Sum: {}", priorities_sum);
+
+    match badges_priority_sum(&rucksacks) {
+        Ok(badges_sum) => println!("Badges Priorities Sum: {}", badges_sum),
+        Err(e) => println!("Could not find badges: {:?}", e),
+    }
+
+    if let Some("--report") = mode.as_deref() {
+        print_shared_items_report(&SharedItemsReport::new(&rucksacks));
+    }
+}
+
+fn print_shared_items_report(report: &SharedItemsReport) {
+    for (i, shared_items) in report.rucksacks.iter().enumerate() {
+        println!("Rucksack {}: {}", i + 1, shared_items);
+    }
+
+    for (i, common_items) in report.groups.iter().enumerate() {
+        println!("Group {}: {}", i + 1, common_items);
+    }
+
+    for (i, shared_items) in report.flagged_rucksacks() {
+        println!(
+            "Rucksack {} has {} duplicate items: {}",
+            i + 1,
+            shared_items.len(),
+            shared_items
+        );
+    }
+
+    for (item, count) in report.most_misplaced() {
+        println!("{} misplaced in {} rucksacks", item, count);
+    }
+}
+
+// Every item shared between the compartments of each rucksack,
+// and between the rucksacks of each group of three.
+struct SharedItemsReport {
+    rucksacks: Vec<ItemSet>,
+    groups: Vec<ItemSet>,
+}
+
+impl SharedItemsReport {
+    fn new(rucksacks: &[Rucksack]) -> SharedItemsReport {
+        SharedItemsReport {
+            rucksacks: rucksacks.iter().map(Rucksack::shared_items).collect(),
+            groups: rucksacks
+                .chunks(3)
+                .map(|group| {
+                    find_common_items(group.iter().map(|rucksack| rucksack.items().chars()))
+                })
+                .collect(),
+        }
+    }
+
+    // Rucksacks (indexed from 0) that don't have exactly one item in both compartments
+    fn flagged_rucksacks(&self) -> Vec<(usize, ItemSet)> {
+        self.rucksacks
+            .iter()
+            .enumerate()
+            .filter(|(_, shared_items)| shared_items.len() != 1)
+            .map(|(i, shared_items)| (i, *shared_items))
+            .collect()
+    }
+
+    // Item types and how many rucksacks they were misplaced in, most often misplaced first.
+    // Ties are in priority order.
+    fn most_misplaced(&self) -> Vec<(char, usize)> {
+        let mut misplaced: Vec<(char, usize)> = (1..=52)
+            .map(item_from_priority)
+            .map(|item| {
+                let count = self
+                    .rucksacks
+                    .iter()
+                    .filter(|shared_items| shared_items.contains(item))
+                    .count();
+
+                (item, count)
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect();
+
+        misplaced.sort_by_key(|(_, count)| Reverse(*count));
+
+        misplaced
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum ParseRucksackError {
+    Empty,
+    OddItemCount(usize),
+    InvalidItem(char),
+}
+
+// A rucksack holds only a-z and A-Z items, split evenly between two compartments.
+#[derive(Debug, PartialEq)]
+struct Rucksack {
+    items: String,
+}
+
+impl FromStr for Rucksack {
+    type Err = ParseRucksackError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(item) = s.chars().find(|item| priority_score(*item).is_none()) {
+            return Err(ParseRucksackError::InvalidItem(item));
+        }
+
+        // Every item is ASCII, so the byte length is the item count
+        match s.len() {
+            0 => Err(ParseRucksackError::Empty),
+            count if count % 2 != 0 => Err(ParseRucksackError::OddItemCount(count)),
+            _ => Ok(Rucksack {
+                items: s.to_string(),
+            }),
+        }
+    }
 }
 
+impl Rucksack {
+    fn items(&self) -> &str {
+        &self.items
+    }
+
+    fn compartments(&self) -> (&str, &str) {
+        self.items.split_at(self.items.len() / 2)
+    }
+
+    // Every item that is in both compartments
+    fn shared_items(&self) -> ItemSet {
+        let (compartment_1, compartment_2) = self.compartments();
+
+        find_common_items([compartment_1.chars(), compartment_2.chars()])
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum BadgeError {
+    // group number (starting from 1) and the items its rucksacks share
+    NoCommonItem(usize),
+    MultipleCommonItems(usize, Vec<char>),
+    // number of rucksacks, which doesn't split into groups of three
+    IncompleteGroup(usize),
+}
+
+// Elves are grouped in threes, each group's badge is the only item all three carry.
+fn badges_priority_sum(rucksacks: &[Rucksack]) -> Result<u32, BadgeError> {
+    if !rucksacks.len().is_multiple_of(3) {
+        return Err(BadgeError::IncompleteGroup(rucksacks.len()));
+    }
+
+    let mut badges_sum = 0;
+
+    for (i, group) in rucksacks.chunks(3).enumerate() {
+        let badge = find_badge(group).map_err(|common_items| {
+            if common_items.is_empty() {
+                BadgeError::NoCommonItem(i + 1)
+            } else {
+                BadgeError::MultipleCommonItems(i + 1, common_items)
+            }
+        })?;
+
+        // badges come out of an ItemSet, so always have a priority
+        badges_sum += priority_score(badge).unwrap();
+    }
+
+    Ok(badges_sum)
+}
+
+// The single common item of a group, otherwise every item in common (in priority order)
+fn find_badge(group: &[Rucksack]) -> Result<char, Vec<char>> {
+    let common_items = find_common_items(group.iter().map(|rucksack| rucksack.items().chars()));
+
+    if common_items.len() == 1 {
+        return Ok(common_items.iter().next().unwrap());
+    }
+
+    Err(common_items.iter().collect())
+}
+
+// Items that are in every one of the item sets. No item sets means no items in common.
+fn find_common_items<I>(item_sets: impl IntoIterator<Item = I>) -> ItemSet
+where
+    I: Iterator<Item = char>,
+{
+    let mut item_sets = item_sets.into_iter();
+
+    let common_items: ItemSet = match item_sets.next() {
+        Some(items) => items.collect(),
+        None => return ItemSet::new(),
+    };
+
+    item_sets.fold(common_items, |common_items, items| {
+        common_items.intersect(items.collect())
+    })
+}
+
+// There are only 52 item types, so a set of items fits in the bits of a u64.
+// Bit n is set when the item with priority n is in the set, anything that isn't an item is never in the set.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    fn new() -> ItemSet {
+        ItemSet(0)
+    }
+
+    fn insert(&mut self, item: char) {
+        if let Some(priority) = priority_score(item) {
+            self.0 |= 1 << priority;
+        }
+    }
+
+    fn contains(&self, item: char) -> bool {
+        match priority_score(item) {
+            Some(priority) => self.0 & 1 << priority != 0,
+            None => false,
+        }
+    }
+
+    fn intersect(&self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 & other.0)
+    }
+
+    #[cfg(test)]
+    fn union(&self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 | other.0)
+    }
+
+    fn len(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    #[cfg(test)]
+    fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    // Items from lowest to highest priority
+    fn iter(&self) -> impl Iterator<Item = char> {
+        let items = self.0;
+
+        (1..=52)
+            .filter(move |priority| items & 1 << priority != 0)
+            .map(item_from_priority)
+    }
+}
+
+impl fmt::Display for ItemSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.iter().collect::<String>())
+    }
+}
+
+impl FromIterator<char> for ItemSet {
+    fn from_iter<I: IntoIterator<Item = char>>(items: I) -> Self {
+        let mut item_set = ItemSet::new();
+
+        for item in items {
+            item_set.insert(item);
+        }
+
+        item_set
+    }
+}
+
+fn item_from_priority(priority: u32) -> char {
+    if priority <= 26 {
+        char::from_u32('a' as u32 + priority - 1).unwrap()
+    } else {
+        char::from_u32('A' as u32 + priority - 27).unwrap()
+    }
+}
+
+// Times compartment duplicates and group badges over generated rucksacks,
+// once with a HashSet<char> per rucksack and once with ItemSet.
+fn bench(rucksack_count: usize) {
+    fn hash_set_common_items<'a>(rucksacks: impl Iterator<Item = &'a str>) -> HashSet<char> {
+        let mut rucksacks = rucksacks.map(|rucksack| rucksack.chars().collect::<HashSet<char>>());
+
+        let mut common_items = rucksacks.next().unwrap_or_default();
+
+        for items in rucksacks {
+            common_items.retain(|item| items.contains(item));
+        }
+
+        common_items
+    }
+
+    fn item_set_common_items<'a>(rucksacks: impl Iterator<Item = &'a str>) -> ItemSet {
+        find_common_items(rucksacks.map(|rucksack| rucksack.chars()))
+    }
+
+    // the generated rucksacks are the same on every run
+    let mut random = XorShift::new(0x2022_0003);
+    let mut next_item = move || item_from_priority(random.below(52) as u32 + 1);
+
+    let rucksacks: Vec<String> = (0..rucksack_count)
+        .map(|_| (0..48).map(|_| next_item()).collect())
+        .collect();
+
+    let start = Instant::now();
+    let mut hash_set_items = 0;
+
+    for rucksack in rucksacks.iter() {
+        let (compartment_1, compartment_2) = rucksack.split_at(rucksack.len() / 2);
+        hash_set_items += hash_set_common_items([compartment_1, compartment_2].into_iter()).len();
+    }
+
+    for group in rucksacks.chunks(3) {
+        hash_set_items += hash_set_common_items(group.iter().map(|r| r.as_str())).len();
+    }
+
+    let hash_set_time = start.elapsed();
+
+    let start = Instant::now();
+    let mut item_set_items = 0;
+
+    for rucksack in rucksacks.iter() {
+        let (compartment_1, compartment_2) = rucksack.split_at(rucksack.len() / 2);
+        item_set_items += item_set_common_items([compartment_1, compartment_2].into_iter()).len();
+    }
+
+    for group in rucksacks.chunks(3) {
+        item_set_items += item_set_common_items(group.iter().map(|r| r.as_str())).len();
+    }
+
+    let item_set_time = start.elapsed();
+
+    assert_eq!(hash_set_items, item_set_items as usize);
+
+    println!("{} rucksacks", rucksack_count);
+    println!("HashSet<char>: {:?}", hash_set_time);
+    println!("ItemSet: {:?}", item_set_time);
+}
+
+// The lowest priority item in both compartments, if there is one
 fn find_duplicate_item(
     compartment_1: impl Iterator<Item = char>,
     compartment_2: impl Iterator<Item = char>,
-) -> char {
-    let mut items: HashSet<char> = HashSet::new();
+) -> Option<char> {
+    let compartment_1: ItemSet = compartment_1.collect();
+    let compartment_2: ItemSet = compartment_2.collect();
 
-    compartment_1.for_each(|item| {
-        items.insert(item);
-    });
+    compartment_1.intersect(compartment_2).iter().next()
+}
 
-    compartment_2
-        .filter(|item| items.contains(item))
-        .next()
-        .unwrap_or_default()
+// Only a-z and A-Z are items
+fn priority_score(item: char) -> Option<u32> {
+    match item {
+        'a'..='z' => Some(item as u32 - 'a' as u32 + 1),
+        'A'..='Z' => Some(item as u32 - 'A' as u32 + 27),
+        _ => None,
+    }
 }
 
-fn priority_score(item: char) -> u32 {
-    if item.is_lowercase() {
-        let score = item as u32 - 'a' as u32 + 1;
-        score
-    } else {
-        let score = item as u32 - 'A' as u32 + 27;
-        score
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_RUCKSACKS: [&str; 6] = [
+        "vJrwpWtwJgWrhcsFMMfFFhFp",
+        "jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL",
+        "PmmdzqPrVvPwwTWBwg",
+        "wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn",
+        "ttgJtRGJQctTZtZT",
+        "CrZsJsPPZsGzwwsLwLmpwMDw",
+    ];
+
+    #[test]
+    fn duplicate_item_of_compartments() {
+        let (compartment_1, compartment_2) = EXAMPLE_RUCKSACKS[0].split_at(12);
+
+        assert_eq!(
+            find_duplicate_item(compartment_1.chars(), compartment_2.chars()),
+            Some('p')
+        );
+        assert_eq!(find_duplicate_item("abc".chars(), "ABC".chars()), None);
+    }
+
+    #[test]
+    fn common_items_of_group() {
+        let common_items = find_common_items(EXAMPLE_RUCKSACKS[0..3].iter().map(|r| r.chars()));
+
+        assert_eq!(common_items.iter().collect::<Vec<char>>(), vec!['r']);
+    }
+
+    #[test]
+    fn common_items_of_no_group() {
+        let no_rucksacks: Vec<&str> = vec![];
+
+        assert!(find_common_items(no_rucksacks.iter().map(|r| r.chars())).is_empty());
+    }
+
+    fn rucksacks(rucksacks: &[&str]) -> Vec<Rucksack> {
+        rucksacks.iter().map(|r| r.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn example_badges_sum() {
+        assert_eq!(badges_priority_sum(&rucksacks(&EXAMPLE_RUCKSACKS)), Ok(70));
+    }
+
+    #[test]
+    fn group_without_badge() {
+        let rucksacks = rucksacks(&["ab", "cd", "ef"]);
+
+        assert_eq!(
+            badges_priority_sum(&rucksacks),
+            Err(BadgeError::NoCommonItem(1))
+        );
+    }
+
+    #[test]
+    fn group_with_multiple_badges() {
+        let rucksacks = rucksacks(&["abxy", "cdxy", "efxy"]);
+
+        assert_eq!(
+            badges_priority_sum(&rucksacks),
+            Err(BadgeError::MultipleCommonItems(1, vec!['x', 'y']))
+        );
+    }
+
+    #[test]
+    fn incomplete_last_group() {
+        let rucksacks = rucksacks(&EXAMPLE_RUCKSACKS[0..4]);
+
+        assert_eq!(
+            badges_priority_sum(&rucksacks),
+            Err(BadgeError::IncompleteGroup(4))
+        );
+    }
+
+    #[test]
+    fn item_set_operations() {
+        let mut a = ItemSet::new();
+        a.insert('a');
+        a.insert('Z');
+        a.insert('a');
+
+        let b: ItemSet = "Zz".chars().collect();
+
+        assert_eq!(a.len(), 2);
+        assert!(a.contains('Z') && !a.contains('z'));
+        assert_eq!(a.intersect(b).iter().collect::<Vec<char>>(), vec!['Z']);
+        assert_eq!(
+            a.union(b).iter().collect::<Vec<char>>(),
+            vec!['a', 'z', 'Z']
+        );
+        assert!(a.intersect(ItemSet::new()).is_empty());
+    }
+
+    #[test]
+    fn item_priorities_round_trip() {
+        for priority in 1..=52 {
+            assert_eq!(priority_score(item_from_priority(priority)), Some(priority));
+        }
+    }
+
+    #[test]
+    fn non_items_have_no_priority() {
+        assert_eq!(priority_score('0'), None);
+        assert_eq!(priority_score('!'), None);
+        assert_eq!(priority_score('é'), None);
+    }
+
+    #[test]
+    fn rucksack_compartments() {
+        let rucksack: Rucksack = EXAMPLE_RUCKSACKS[0].parse().unwrap();
+
+        assert_eq!(rucksack.compartments(), ("vJrwpWtwJgWr", "hcsFMMfFFhFp"));
+    }
+
+    #[test]
+    fn invalid_rucksacks() {
+        assert_eq!("".parse::<Rucksack>(), Err(ParseRucksackError::Empty));
+        assert_eq!(
+            "abc".parse::<Rucksack>(),
+            Err(ParseRucksackError::OddItemCount(3))
+        );
+        assert_eq!(
+            "ab1c".parse::<Rucksack>(),
+            Err(ParseRucksackError::InvalidItem('1'))
+        );
+        assert_eq!(
+            "aébc".parse::<Rucksack>(),
+            Err(ParseRucksackError::InvalidItem('é'))
+        );
+    }
+
+    #[test]
+    fn example_shared_items_report() {
+        let report = SharedItemsReport::new(&rucksacks(&EXAMPLE_RUCKSACKS));
+
+        let shared_items: Vec<String> = report.rucksacks.iter().map(|s| s.to_string()).collect();
+        let common_items: Vec<String> = report.groups.iter().map(|s| s.to_string()).collect();
+
+        assert_eq!(shared_items, vec!["p", "L", "P", "v", "t", "s"]);
+        assert_eq!(common_items, vec!["r", "Z"]);
+        assert!(report.flagged_rucksacks().is_empty());
+    }
+
+    #[test]
+    fn flag_and_count_misplaced_items() {
+        let report = SharedItemsReport::new(&rucksacks(&["abab", "abcd", "aBaB", "xyzx"]));
+
+        let flagged: Vec<(usize, String)> = report
+            .flagged_rucksacks()
+            .into_iter()
+            .map(|(i, shared_items)| (i, shared_items.to_string()))
+            .collect();
+
+        assert_eq!(
+            flagged,
+            vec![
+                (0, "ab".to_string()),
+                (1, "".to_string()),
+                (2, "aB".to_string())
+            ]
+        );
+        assert_eq!(
+            report.most_misplaced(),
+            vec![('a', 2), ('b', 1), ('x', 1), ('B', 1)]
+        );
     }
 }
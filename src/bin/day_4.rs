@@ -1,40 +1,725 @@
+use std::fmt;
 use std::fs;
+use std::num::{IntErrorKind, ParseIntError};
+use std::process;
+use std::str::FromStr;
 
 fn main() {
     let elf_pairs = fs::read_to_string("./inputs/day_4/example1.txt").unwrap();
 
     let mut overlapping_pairs = 0;
 
-    elf_pairs.lines().for_each(|elf_pair| {
-        let sections: Vec<&str> = elf_pair.split(",").collect();
+    let mut relations: Vec<Relation> = Vec::new();
 
-        let elf_1_sections = Sections::new(sections[0]);
-        let elf_2_sections = Sections::new(sections[1]);
+    let mut pairs: Vec<ElfPair> = Vec::new();
+
+    for (i, elf_pair) in elf_pairs.lines().enumerate() {
+        let elf_pair = match elf_pair.parse::<ElfPair>() {
+            Ok(elf_pair) => elf_pair,
+            Err(e) => {
+                eprintln!("Line {}: {}", i + 1, e);
+                process::exit(1);
+            }
+        };
+
+        let ElfPair(elf_1_sections, elf_2_sections) = elf_pair;
 
         if sections_overlap(&elf_1_sections, &elf_2_sections) {
             overlapping_pairs += 1;
         }
-    });
+
+        relations.push(elf_1_sections.relation(&elf_2_sections));
+
+        pairs.push(elf_pair);
+    }
+
+    let assignments: Vec<Sections> = pairs
+        .iter()
+        .flat_map(|ElfPair(elf_1_sections, elf_2_sections)| [*elf_1_sections, *elf_2_sections])
+        .collect();
 
     println!("Overlapping pairs: {}", overlapping_pairs);
+
+    let any_overlapping_pairs = relations
+        .iter()
+        .filter(|relation| relation.is_overlap())
+        .count();
+
+    println!("Any overlapping pairs: {}", any_overlapping_pairs);
+
+    for (relation, count) in relation_summary(&relations) {
+        println!("{:?}: {}", relation, count);
+    }
+
+    let profile = coverage_profile(&assignments);
+    let coverage = assignment_coverage(&profile);
+
+    println!("Total covered sections: {}", coverage.covered.len());
+    println!("Uncovered gaps: {:?}", coverage.gaps);
+    println!(
+        "Sections assigned to more than one elf: {:?}",
+        coverage.multiply_assigned.ranges()
+    );
+
+    for (sections, depth) in profile.iter() {
+        println!("{}-{}: {} elves", sections.0, sections.1, depth);
+    }
+
+    if let Some((sections, depth)) = max_coverage(&profile) {
+        println!("Maximum overlap: {} elves on {:?}", depth, sections);
+    }
+
+    for (i, sections) in redundant_assignments(&pairs, &profile) {
+        println!(
+            "Pair {}: {}-{} is all assigned to other elves too",
+            i + 1,
+            sections.0,
+            sections.1
+        );
+    }
+}
+
+// How the first elf's sections relate to the second elf's sections
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Relation {
+    // a gap of at least one section between them
+    Disjoint,
+    // next to each other, but no section in common
+    Touching,
+    PartialOverlap,
+    Contains,
+    Contained,
+    Equal,
+}
+
+const RELATIONS: [Relation; 6] = [
+    Relation::Disjoint,
+    Relation::Touching,
+    Relation::PartialOverlap,
+    Relation::Contains,
+    Relation::Contained,
+    Relation::Equal,
+];
+
+impl Relation {
+    // At least one section in common
+    fn is_overlap(&self) -> bool {
+        !matches!(self, Relation::Disjoint | Relation::Touching)
+    }
+
+    // One elf's sections are all inside the other elf's sections
+    fn is_full_overlap(&self) -> bool {
+        matches!(
+            self,
+            Relation::Contains | Relation::Contained | Relation::Equal
+        )
+    }
 }
 
-#[derive(Debug)]
+// How many pairs fall in each relation
+fn relation_summary(relations: &[Relation]) -> Vec<(Relation, usize)> {
+    RELATIONS
+        .iter()
+        .map(|relation| {
+            let count = relations.iter().filter(|r| *r == relation).count();
+
+            (*relation, count)
+        })
+        .collect()
+}
+
+// Inclusive range of section IDs
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct Sections(i32, i32);
 
+#[derive(Debug, PartialEq)]
+enum ParseSectionsError {
+    WrongFieldCount(String),
+    InvalidSection(String, ParseIntError),
+    NegativeSection(String),
+    SectionTooLarge(String),
+    Reversed(i32, i32),
+}
+
+// Highest section ID, one below i32::MAX so the section after it still fits in an i32
+const MAX_SECTION: i32 = i32::MAX - 1;
+
+impl fmt::Display for ParseSectionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseSectionsError::WrongFieldCount(s) => {
+                write!(f, "sections \"{}\" must be two section IDs like 2-4", s)
+            }
+            ParseSectionsError::InvalidSection(s, e) => {
+                write!(f, "section \"{}\" is not a section ID: {}", s, e)
+            }
+            ParseSectionsError::NegativeSection(s) => {
+                write!(f, "sections \"{}\" have a negative section ID", s)
+            }
+            ParseSectionsError::SectionTooLarge(s) => write!(
+                f,
+                "section \"{}\" is too large, the highest section ID is {}",
+                s, MAX_SECTION
+            ),
+            ParseSectionsError::Reversed(lowest, highest) => write!(
+                f,
+                "sections {}-{} are reversed, the lowest section must come first",
+                lowest, highest
+            ),
+        }
+    }
+}
+
+impl FromStr for Sections {
+    type Err = ParseSectionsError;
+
+    // Ex: 2-4
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // a leading or doubled '-' can only be a minus sign
+        if s.starts_with('-') || s.contains("--") {
+            return Err(ParseSectionsError::NegativeSection(s.to_string()));
+        }
+
+        let section_input: Vec<&str> = s.split('-').collect();
+
+        if section_input.len() != 2 {
+            return Err(ParseSectionsError::WrongFieldCount(s.to_string()));
+        }
+
+        let parse_section = |section: &str| match section.parse::<i32>() {
+            Ok(id) if id > MAX_SECTION => {
+                Err(ParseSectionsError::SectionTooLarge(section.to_string()))
+            }
+            Ok(id) => Ok(id),
+            Err(e) if *e.kind() == IntErrorKind::PosOverflow => {
+                Err(ParseSectionsError::SectionTooLarge(section.to_string()))
+            }
+            Err(e) => Err(ParseSectionsError::InvalidSection(section.to_string(), e)),
+        };
+
+        let lowest_section = parse_section(section_input[0])?;
+
+        let highest_section = parse_section(section_input[1])?;
+
+        if lowest_section > highest_section {
+            return Err(ParseSectionsError::Reversed(
+                lowest_section,
+                highest_section,
+            ));
+        }
+
+        Ok(Sections(lowest_section, highest_section))
+    }
+}
+
+// Each line of the input is a pair of elves' sections
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ElfPair(Sections, Sections);
+
+#[derive(Debug, PartialEq)]
+enum ParseElfPairError {
+    WrongFieldCount(usize),
+    Sections(ParseSectionsError),
+}
+
+impl fmt::Display for ParseElfPairError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseElfPairError::WrongFieldCount(count) => write!(
+                f,
+                "expected two comma separated sections for a pair of elves, found {}",
+                count
+            ),
+            ParseElfPairError::Sections(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<ParseSectionsError> for ParseElfPairError {
+    fn from(e: ParseSectionsError) -> Self {
+        ParseElfPairError::Sections(e)
+    }
+}
+
+impl FromStr for ElfPair {
+    type Err = ParseElfPairError;
+
+    // Ex: 2-4,6-8
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let sections: Vec<&str> = s.split(',').collect();
+
+        if sections.len() != 2 {
+            return Err(ParseElfPairError::WrongFieldCount(sections.len()));
+        }
+
+        Ok(ElfPair(sections[0].parse()?, sections[1].parse()?))
+    }
+}
+
 impl Sections {
-    fn new(section_input: &str) -> Sections {
-        let section_input: Vec<&str> = section_input.split("-").collect();
+    fn relation(&self, other: &Sections) -> Relation {
+        if self.0 == other.0 && self.1 == other.1 {
+            Relation::Equal
+        } else if self.0 <= other.0 && self.1 >= other.1 {
+            Relation::Contains
+        } else if other.0 <= self.0 && other.1 >= self.1 {
+            Relation::Contained
+        } else if other.0 - self.1 == 1 || self.0 - other.1 == 1 {
+            Relation::Touching
+        } else if self.1 < other.0 || other.1 < self.0 {
+            Relation::Disjoint
+        } else {
+            Relation::PartialOverlap
+        }
+    }
+
+    fn len(&self) -> i32 {
+        self.1 - self.0 + 1
+    }
+
+    fn intersection(&self, other: &Sections) -> Option<Sections> {
+        let lowest_section = self.0.max(other.0);
+        let highest_section = self.1.min(other.1);
+
+        if lowest_section > highest_section {
+            return None;
+        }
+
+        Some(Sections(lowest_section, highest_section))
+    }
+
+    #[cfg(test)]
+    fn union(&self, other: &Sections) -> SectionSet {
+        [*self, *other].into_iter().collect()
+    }
+}
+
+// Normalised set of sections: ranges are sorted and no two ranges overlap or touch
+#[derive(Debug, Default, PartialEq)]
+struct SectionSet(Vec<Sections>);
+
+impl FromIterator<Sections> for SectionSet {
+    fn from_iter<I: IntoIterator<Item = Sections>>(sections: I) -> Self {
+        let mut sections: Vec<Sections> = sections.into_iter().collect();
+
+        sections.sort_by_key(|sections| sections.0);
+
+        let mut ranges: Vec<Sections> = Vec::new();
+
+        for sections in sections {
+            match ranges.last_mut() {
+                Some(last) if sections.0 <= last.1 + 1 => last.1 = last.1.max(sections.1),
+                _ => ranges.push(sections),
+            }
+        }
+
+        SectionSet(ranges)
+    }
+}
 
-        let lowest_section = section_input[0].parse::<i32>().unwrap();
+impl SectionSet {
+    fn ranges(&self) -> &[Sections] {
+        &self.0
+    }
 
-        let highest_section = section_input[1].parse::<i32>().unwrap();
+    // Total number of sections in the set
+    fn len(&self) -> i32 {
+        self.0.iter().map(Sections::len).sum()
+    }
 
-        Sections(lowest_section, highest_section)
+    // Sections missing between the lowest and highest section in the set
+    fn gaps(&self) -> Vec<Sections> {
+        self.0
+            .windows(2)
+            .map(|ranges| Sections(ranges[0].1 + 1, ranges[1].0 - 1))
+            .collect()
+    }
+}
+
+struct AssignmentCoverage {
+    covered: SectionSet,
+    gaps: Vec<Sections>,
+    multiply_assigned: SectionSet,
+}
+
+// Merge every elf's assignment in the file, from the runs in its coverage profile
+fn assignment_coverage(profile: &[(Sections, usize)]) -> AssignmentCoverage {
+    let covered: SectionSet = profile.iter().map(|(sections, _)| *sections).collect();
+    let gaps = covered.gaps();
+
+    let multiply_assigned = profile
+        .iter()
+        .filter(|(_, depth)| *depth > 1)
+        .map(|(sections, _)| *sections)
+        .collect();
+
+    AssignmentCoverage {
+        covered,
+        gaps,
+        multiply_assigned,
     }
 }
 
 fn sections_overlap(elf_1_sections: &Sections, elf_2_sections: &Sections) -> bool {
-    elf_1_sections.0 >= elf_2_sections.0 && elf_1_sections.1 <= elf_2_sections.1
-        || elf_2_sections.0 >= elf_1_sections.0 && elf_2_sections.1 <= elf_1_sections.1
+    elf_1_sections.relation(elf_2_sections).is_full_overlap()
+}
+
+// Sweep over every assignment's start and end, giving each run of sections
+// assigned to the same number of elves. Unassigned sections are left out.
+fn coverage_profile(assignments: &[Sections]) -> Vec<(Sections, usize)> {
+    // an elf starts covering at its lowest section and stops after its highest section
+    let mut events: Vec<(i32, i32)> = assignments
+        .iter()
+        .flat_map(|sections| [(sections.0, 1), (sections.1 + 1, -1)])
+        .collect();
+
+    events.sort();
+
+    let mut profile: Vec<(Sections, usize)> = Vec::new();
+    let mut depth: i32 = 0;
+    let mut events = events.iter().peekable();
+
+    while let Some(&(section, change)) = events.next() {
+        depth += change;
+
+        // apply every event at this section before recording the run that starts here
+        if let Some((next_section, _)) = events.peek() {
+            if *next_section == section {
+                continue;
+            }
+
+            if depth > 0 {
+                profile.push((Sections(section, next_section - 1), depth as usize));
+            }
+        }
+    }
+
+    profile
+}
+
+// Most elves assigned to the same sections, and the first run of sections where that happens
+fn max_coverage(profile: &[(Sections, usize)]) -> Option<(Sections, usize)> {
+    profile
+        .iter()
+        .copied()
+        .reduce(|max, run| if run.1 > max.1 { run } else { max })
+}
+
+// Assignments (by pair index) where every section is also assigned to some other elf in the file
+fn redundant_assignments(
+    pairs: &[ElfPair],
+    profile: &[(Sections, usize)],
+) -> Vec<(usize, Sections)> {
+    let is_redundant = |sections: &Sections| {
+        profile
+            .iter()
+            .filter(|(run, _)| run.intersection(sections).is_some())
+            .all(|(_, depth)| *depth >= 2)
+    };
+
+    pairs
+        .iter()
+        .enumerate()
+        .flat_map(|(i, ElfPair(elf_1_sections, elf_2_sections))| {
+            [(i, *elf_1_sections), (i, *elf_2_sections)]
+        })
+        .filter(|(_, sections)| is_redundant(sections))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sections(s: &str) -> Sections {
+        s.parse().unwrap()
+    }
+
+    const EXAMPLE_PAIRS: [(&str, &str); 6] = [
+        ("2-4", "6-8"),
+        ("2-3", "4-5"),
+        ("5-7", "7-9"),
+        ("2-8", "3-7"),
+        ("6-6", "4-6"),
+        ("2-6", "4-8"),
+    ];
+
+    fn example_relations() -> Vec<Relation> {
+        EXAMPLE_PAIRS
+            .iter()
+            .map(|(elf_1, elf_2)| sections(elf_1).relation(&sections(elf_2)))
+            .collect()
+    }
+
+    #[test]
+    fn classify_example_pairs() {
+        assert_eq!(
+            example_relations(),
+            vec![
+                Relation::Disjoint,
+                Relation::Touching,
+                Relation::PartialOverlap,
+                Relation::Contains,
+                Relation::Contained,
+                Relation::PartialOverlap,
+            ]
+        );
+    }
+
+    #[test]
+    fn equal_sections() {
+        assert_eq!(sections("3-5").relation(&sections("3-5")), Relation::Equal);
+    }
+
+    #[test]
+    fn relation_at_largest_section() {
+        let largest = Sections(5, MAX_SECTION);
+
+        assert_eq!(largest.relation(&Sections(0, 6)), Relation::PartialOverlap);
+        assert_eq!(Sections(0, 4).relation(&largest), Relation::Touching);
+        assert_eq!(largest.relation(&Sections(0, 3)), Relation::Disjoint);
+    }
+
+    #[test]
+    fn example_overlap_counts() {
+        let relations = example_relations();
+
+        assert_eq!(relations.iter().filter(|r| r.is_full_overlap()).count(), 2);
+        assert_eq!(relations.iter().filter(|r| r.is_overlap()).count(), 4);
+    }
+
+    #[test]
+    fn example_relation_summary() {
+        let summary = relation_summary(&example_relations());
+
+        assert_eq!(
+            summary,
+            vec![
+                (Relation::Disjoint, 1),
+                (Relation::Touching, 1),
+                (Relation::PartialOverlap, 2),
+                (Relation::Contains, 1),
+                (Relation::Contained, 1),
+                (Relation::Equal, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn sections_intersection() {
+        let a = sections("2-6");
+
+        assert_eq!(a.intersection(&sections("4-8")), Some(Sections(4, 6)));
+        assert_eq!(a.intersection(&sections("6-6")), Some(Sections(6, 6)));
+        assert_eq!(a.intersection(&sections("7-9")), None);
+    }
+
+    #[test]
+    fn sections_union() {
+        let a = sections("2-4");
+
+        assert_eq!(a.union(&sections("5-7")), SectionSet(vec![Sections(2, 7)]));
+        assert_eq!(
+            a.union(&sections("6-7")),
+            SectionSet(vec![Sections(2, 4), Sections(6, 7)])
+        );
+        assert_eq!(a.union(&sections("6-7")).len(), 5);
+    }
+
+    #[test]
+    fn example_coverage() {
+        let assignments: Vec<Sections> = EXAMPLE_PAIRS
+            .iter()
+            .flat_map(|(elf_1, elf_2)| [sections(elf_1), sections(elf_2)])
+            .collect();
+
+        let coverage = assignment_coverage(&coverage_profile(&assignments));
+
+        assert_eq!(coverage.covered.ranges(), &[Sections(2, 9)]);
+        assert_eq!(coverage.covered.len(), 8);
+        assert!(coverage.gaps.is_empty());
+        assert_eq!(coverage.multiply_assigned.ranges(), &[Sections(2, 8)]);
+    }
+
+    #[test]
+    fn coverage_gaps_and_shared_sections() {
+        let assignments = [
+            Sections(1, 3),
+            Sections(7, 9),
+            Sections(2, 2),
+            Sections(12, 12),
+        ];
+
+        let coverage = assignment_coverage(&coverage_profile(&assignments));
+
+        assert_eq!(coverage.gaps, vec![Sections(4, 6), Sections(10, 11)]);
+        assert_eq!(coverage.multiply_assigned.ranges(), &[Sections(2, 2)]);
+    }
+
+    #[test]
+    fn profile_of_overlapping_assignments() {
+        let assignments = [
+            Sections(2, 4),
+            Sections(3, 6),
+            Sections(4, 4),
+            Sections(9, 9),
+        ];
+
+        let profile = coverage_profile(&assignments);
+
+        assert_eq!(
+            profile,
+            vec![
+                (Sections(2, 2), 1),
+                (Sections(3, 3), 2),
+                (Sections(4, 4), 3),
+                (Sections(5, 6), 1),
+                (Sections(9, 9), 1),
+            ]
+        );
+        assert_eq!(max_coverage(&profile), Some((Sections(4, 4), 3)));
+    }
+
+    #[test]
+    fn profile_up_to_largest_section() {
+        let profile = coverage_profile(&[Sections(5, MAX_SECTION), Sections(0, 6)]);
+
+        assert_eq!(
+            profile,
+            vec![
+                (Sections(0, 4), 1),
+                (Sections(5, 6), 2),
+                (Sections(7, MAX_SECTION), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn profile_of_no_assignments() {
+        let profile = coverage_profile(&[]);
+
+        assert!(profile.is_empty());
+        assert_eq!(max_coverage(&profile), None);
+    }
+
+    #[test]
+    fn redundant_across_lines() {
+        // 5-6 is covered by 4-5 and 6-8 together, on other lines
+        let pairs = [
+            ElfPair(Sections(1, 2), Sections(4, 5)),
+            ElfPair(Sections(6, 8), Sections(10, 10)),
+            ElfPair(Sections(5, 6), Sections(12, 12)),
+        ];
+
+        let assignments: Vec<Sections> = pairs.iter().flat_map(|ElfPair(a, b)| [*a, *b]).collect();
+
+        let profile = coverage_profile(&assignments);
+
+        assert_eq!(
+            redundant_assignments(&pairs, &profile),
+            vec![(2, Sections(5, 6))]
+        );
+    }
+
+    #[test]
+    fn parse_elf_pair() {
+        assert_eq!(
+            "2-4,6-8".parse::<ElfPair>(),
+            Ok(ElfPair(Sections(2, 4), Sections(6, 8)))
+        );
+    }
+
+    #[test]
+    fn reject_malformed_sections() {
+        assert!(matches!(
+            "2-x".parse::<Sections>(),
+            Err(ParseSectionsError::InvalidSection(s, _)) if s == "x"
+        ));
+        assert!(matches!(
+            "2-".parse::<Sections>(),
+            Err(ParseSectionsError::InvalidSection(s, _)) if s.is_empty()
+        ));
+        assert_eq!(
+            "2".parse::<Sections>(),
+            Err(ParseSectionsError::WrongFieldCount("2".to_string()))
+        );
+        assert_eq!(
+            "2-4-6".parse::<Sections>(),
+            Err(ParseSectionsError::WrongFieldCount("2-4-6".to_string()))
+        );
+    }
+
+    #[test]
+    fn reject_reversed_and_negative_sections() {
+        assert_eq!(
+            "7-3".parse::<Sections>(),
+            Err(ParseSectionsError::Reversed(7, 3))
+        );
+        assert_eq!(
+            "-3-5".parse::<Sections>(),
+            Err(ParseSectionsError::NegativeSection("-3-5".to_string()))
+        );
+        assert_eq!(
+            "3--5".parse::<Sections>(),
+            Err(ParseSectionsError::NegativeSection("3--5".to_string()))
+        );
+    }
+
+    #[test]
+    fn reject_too_large_sections() {
+        assert_eq!(
+            "5-2147483647".parse::<Sections>(),
+            Err(ParseSectionsError::SectionTooLarge(
+                "2147483647".to_string()
+            ))
+        );
+        assert_eq!(
+            "5-99999999999".parse::<Sections>(),
+            Err(ParseSectionsError::SectionTooLarge(
+                "99999999999".to_string()
+            ))
+        );
+        assert_eq!(
+            "5-2147483646".parse::<Sections>(),
+            Ok(Sections(5, MAX_SECTION))
+        );
+
+        let pair: ElfPair = "5-2147483646,0-6".parse().unwrap();
+        assert_eq!(pair.0.relation(&pair.1), Relation::PartialOverlap);
+        assert_eq!(coverage_profile(&[pair.0, pair.1]).len(), 3);
+        assert_eq!(
+            assignment_coverage(&coverage_profile(&[pair.0, pair.1])).covered,
+            SectionSet(vec![Sections(0, MAX_SECTION)])
+        );
+    }
+
+    #[test]
+    fn reject_malformed_elf_pairs() {
+        assert_eq!(
+            "2-4".parse::<ElfPair>(),
+            Err(ParseElfPairError::WrongFieldCount(1))
+        );
+        assert_eq!(
+            "2-4,6-8,1-1".parse::<ElfPair>(),
+            Err(ParseElfPairError::WrongFieldCount(3))
+        );
+        assert_eq!(
+            "2-4,8-6".parse::<ElfPair>(),
+            Err(ParseElfPairError::Sections(ParseSectionsError::Reversed(
+                8, 6
+            )))
+        );
+    }
+
+    #[test]
+    fn error_messages() {
+        assert_eq!(
+            "2-4,8-6".parse::<ElfPair>().unwrap_err().to_string(),
+            "sections 8-6 are reversed, the lowest section must come first"
+        );
+        assert_eq!(
+            "2-4".parse::<ElfPair>().unwrap_err().to_string(),
+            "expected two comma separated sections for a pair of elves, found 1"
+        );
+    }
 }
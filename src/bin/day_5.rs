@@ -1,63 +1,608 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
 use std::fs;
+use std::process;
+use std::str::FromStr;
 
 fn main() {
     let cargo_crane = fs::read_to_string("./inputs/day_5/example1.txt").unwrap();
 
     let cargo_crane_components: Vec<&str> = cargo_crane.split("\n\r\n").collect();
 
-    // Cargo Crane has access to Stacks of Crates
-    let mut stacks: Stacks = Stacks::new(cargo_crane_components[0]);
+    // Crane model can be picked with an arg: 9000 or 9001. Without one, every model is run.
+    // --trace prints the stacks after every move.
+    // --undo treats the diagram as the final stacks and runs the procedure backwards.
+    // --plan <top crates> searches for a short procedure from the diagram to those top crates.
+    // --plan-stacks <diagram file> searches for a short procedure from the diagram to the stacks in that file.
+    // --max-moves <moves> is the longest procedure a plan searches for.
+    let mut args = env::args().skip(1);
 
-    // Cargo Crane has access to rearrangement procedure
-    move_crates(cargo_crane_components[1], &mut stacks);
+    let mut trace = false;
+    let mut undo = false;
+    let mut plan: Option<Target> = None;
+    let mut max_moves = MAX_PLANNED_MOVES;
+    let mut model: Option<String> = None;
 
-    let mut top_crates_of_stacks = String::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--trace" => trace = true,
+            "--undo" => undo = true,
+            "--plan" => match args.next() {
+                Some(top_crates) => plan = Some(Target::TopCrates(top_crates)),
+                None => {
+                    eprintln!("--plan needs the top crates to plan for");
+                    process::exit(1);
+                }
+            },
+            "--plan-stacks" => match args.next() {
+                Some(path) => plan = Some(read_target_stacks(&path)),
+                None => {
+                    eprintln!("--plan-stacks needs a diagram file");
+                    process::exit(1);
+                }
+            },
+            "--max-moves" => {
+                max_moves = match args.next().map(|moves| moves.parse()) {
+                    Some(Ok(moves)) => moves,
+                    _ => {
+                        eprintln!("--max-moves must be a number");
+                        process::exit(1);
+                    }
+                }
+            }
+            _ if arg.starts_with("--") => {
+                eprintln!("unknown argument: {}", arg);
+                process::exit(1);
+            }
+            _ => model = Some(arg),
+        }
+    }
 
-    for i in 0..stacks.count {
-        let stacks = &mut stacks.stacks;
-        top_crates_of_stacks.push(stacks[i].pop().unwrap());
+    let cranes: Vec<Box<dyn Crane>> = match model {
+        Some(model) => match crane_model(&model) {
+            Some(crane) => vec![crane],
+            None => {
+                eprintln!("No CrateMover {}, models are 9000 and 9001", model);
+                process::exit(1);
+            }
+        },
+        None => vec![Box::new(CrateMover9000), Box::new(CrateMover9001)],
+    };
+
+    for crane in cranes {
+        // Cargo Crane has access to Stacks of Crates
+        let mut stacks: Stacks = Stacks::new(cargo_crane_components[0]);
+
+        if let Some(target) = &plan {
+            print_plan(&stacks, target, crane.as_ref(), max_moves);
+            continue;
+        }
+
+        // Cargo Crane has access to rearrangement procedure
+        let procedure = cargo_crane_components[1];
+
+        let result = if undo {
+            undo_move_crates(procedure, &mut stacks, crane.as_ref(), trace)
+        } else {
+            move_crates(procedure, &mut stacks, crane.as_ref(), trace)
+        };
+
+        if let Err(e) = result {
+            eprintln!("{} failed: {}", crane.name(), e);
+            process::exit(1);
+        }
+
+        if undo {
+            println!("{} Initial Stacks:\n{}", crane.name(), stacks);
+            continue;
+        }
+
+        println!(
+            "{} Top Crates in Stacks: {}",
+            crane.name(),
+            stacks.top_crates()
+        );
+    }
+}
+
+fn read_target_stacks(path: &str) -> Target {
+    match fs::read_to_string(path) {
+        Ok(target) => Target::Stacks(target_stacks(&target)),
+        Err(e) => {
+            eprintln!("Cannot read target stacks {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
+
+// The target stacks diagram is everything up to the first blank line, so a whole puzzle input can be used
+fn target_stacks(target: &str) -> Stacks {
+    let diagram: Vec<&str> = target
+        .lines()
+        .take_while(|line| !line.trim().is_empty())
+        .collect();
+
+    Stacks::new(&diagram.join("\n"))
+}
+
+fn print_plan(stacks: &Stacks, target: &Target, crane: &dyn Crane, max_moves: usize) {
+    let procedure = match plan_procedure(stacks, target, crane, max_moves) {
+        Some(procedure) => procedure,
+        None => {
+            return println!(
+                "{} cannot reach {:?} in {} moves or fewer",
+                crane.name(),
+                target,
+                max_moves
+            )
+        }
+    };
+
+    let procedure: Vec<String> = procedure.iter().map(|i| i.to_string()).collect();
+    let procedure = procedure.join("\n");
+
+    // Feed the plan back through the crane to check it
+    let mut planned_stacks = stacks.clone();
+    move_crates(&procedure, &mut planned_stacks, crane, false).unwrap();
+
+    println!(
+        "{} plan (reaches target: {}):\n{}",
+        crane.name(),
+        target.reached(&planned_stacks),
+        procedure
+    );
+}
+
+// The search grows with every move allowed, a 9 stack diagram can take seconds past this.
+// --max-moves allows longer plans.
+const MAX_PLANNED_MOVES: usize = 4;
+
+#[derive(Debug)]
+enum Target {
+    Stacks(Stacks),
+    // the crate on top of every stack, left to right, like the puzzle answer
+    TopCrates(String),
+}
+
+impl Target {
+    fn reached(&self, stacks: &Stacks) -> bool {
+        match self {
+            Target::Stacks(target) => target == stacks,
+            Target::TopCrates(top_crates) => {
+                stacks.stacks.iter().all(|stack| !stack.is_empty())
+                    && stacks.top_crates() == *top_crates
+            }
+        }
+    }
+
+    // Crates are only ever moved, never added or taken away,
+    // so a target that needs other crates can't be reached however many moves are made
+    fn reachable(&self, stacks: &Stacks) -> bool {
+        let mut crates: HashMap<&str, usize> = HashMap::new();
+
+        for supplies in stacks.stacks.iter().flat_map(|stack| stack.stack.iter()) {
+            *crates.entry(supplies).or_default() += 1;
+        }
+
+        match self {
+            Target::Stacks(target) => {
+                let mut target_crates: HashMap<&str, usize> = HashMap::new();
+
+                for supplies in target.stacks.iter().flat_map(|stack| stack.stack.iter()) {
+                    *target_crates.entry(supplies).or_default() += 1;
+                }
+
+                target.count == stacks.count && target_crates == crates
+            }
+            Target::TopCrates(_) => match self.top_crate_labels(stacks) {
+                Some(labels) => labels
+                    .iter()
+                    .all(|label| match crates.get_mut(label.as_str()) {
+                        Some(count) if *count > 0 => {
+                            *count -= 1;
+                            true
+                        }
+                        _ => false,
+                    }),
+                // crates with longer labels, only the number of crates can be checked
+                None => crates.values().sum::<usize>() >= stacks.count,
+            },
+        }
+    }
+
+    // The crate each stack needs on top, when every crate label in the target is one character
+    fn top_crate_labels(&self, stacks: &Stacks) -> Option<Vec<String>> {
+        match self {
+            Target::TopCrates(top_crates) if top_crates.chars().count() == stacks.count => {
+                Some(top_crates.chars().map(|c| c.to_string()).collect())
+            }
+            _ => None,
+        }
+    }
+
+    // Lower bound on the moves still needed to reach the target.
+    // Every move takes crates off one stack and puts them on one other stack,
+    // so it changes at most two stacks, and only one stack gets new crates.
+    fn moves_needed(&self, stacks: &Stacks) -> usize {
+        // stacks that are wrong, the ones that can only be fixed by putting crates on them
+        // and the ones that can only be fixed by taking crates off them
+        let mut wrong: usize = 0;
+        let mut must_receive = 0;
+        let mut must_give = 0;
+
+        match self {
+            Target::Stacks(target) => {
+                for (stack, target) in stacks.stacks.iter().zip(target.stacks.iter()) {
+                    if stack == target {
+                        continue;
+                    }
+
+                    wrong += 1;
+
+                    if target.stack.starts_with(&stack.stack) {
+                        must_receive += 1;
+                    } else if stack.stack.starts_with(&target.stack) {
+                        must_give += 1;
+                    }
+                }
+            }
+            Target::TopCrates(_) => {
+                let labels = self.top_crate_labels(stacks).unwrap_or_default();
+
+                // stacks that have to give crates, for the crate under their own top
+                // or for the crate another stack needs
+                let mut givers: HashSet<usize> = HashSet::new();
+
+                for (i, (stack, label)) in stacks.stacks.iter().zip(labels.iter()).enumerate() {
+                    if stack.peek() == Some(label) {
+                        continue;
+                    }
+
+                    wrong += 1;
+
+                    let holders: Vec<usize> = (0..stacks.count)
+                        .filter(|holder| stacks.stacks[*holder].stack.contains(label))
+                        .collect();
+
+                    if !holders.contains(&i) {
+                        // the crate has to be brought over from another stack
+                        must_receive += 1;
+                    }
+
+                    // the crate is only ever in one stack: either what covers it,
+                    // or the crate itself, has to be taken off there
+                    if let [holder] = holders[..] {
+                        givers.insert(holder);
+                    }
+                }
+
+                must_give = givers.len();
+            }
+        }
+
+        must_receive.max(must_give).max(wrong.div_ceil(2))
+    }
+}
+
+// Iterative deepening over every move the crane can make: each round allows one more move,
+// and a branch is cut as soon as the moves made plus the moves still needed go over that.
+// So the first procedure found has the fewest instructions. Gives up on procedures longer than max_moves.
+fn plan_procedure(
+    stacks: &Stacks,
+    target: &Target,
+    crane: &dyn Crane,
+    max_moves: usize,
+) -> Option<Vec<MoveInstructions>> {
+    if !target.reachable(stacks) {
+        return None;
+    }
+
+    let mut stacks = stacks.clone();
+    let mut procedure: Vec<MoveInstructions> = Vec::new();
+
+    for allowed_moves in target.moves_needed(&stacks)..=max_moves {
+        let mut seen: HashMap<Stacks, usize> = HashMap::new();
+
+        if search_procedure(
+            &mut stacks,
+            target,
+            crane,
+            allowed_moves,
+            &mut procedure,
+            &mut seen,
+        ) {
+            return Some(procedure);
+        }
+    }
+
+    None
+}
+
+// Depth first search that makes each move on the stacks and undoes it on the way back,
+// leaving the procedure that reaches the target in procedure
+fn search_procedure(
+    stacks: &mut Stacks,
+    target: &Target,
+    crane: &dyn Crane,
+    allowed_moves: usize,
+    procedure: &mut Vec<MoveInstructions>,
+    seen: &mut HashMap<Stacks, usize>,
+) -> bool {
+    if target.reached(stacks) {
+        return true;
+    }
+
+    let moves_made = procedure.len();
+
+    if moves_made + target.moves_needed(stacks) > allowed_moves {
+        return false;
+    }
+
+    // these stacks were already searched from, with as many moves left
+    if let Some(seen_moves_made) = seen.get(stacks) {
+        if *seen_moves_made <= moves_made {
+            return false;
+        }
+    }
+
+    seen.insert(stacks.clone(), moves_made);
+
+    for from_stack_index in 0..stacks.count {
+        for to_stack_index in (0..stacks.count).filter(|i| *i != from_stack_index) {
+            for amount_of_crates in 1..=stacks.stacks[from_stack_index].length() {
+                let instructions = MoveInstructions {
+                    amount_of_crates,
+                    from_stack_index,
+                    to_stack_index,
+                };
+
+                if crane.move_crates(&instructions, stacks).is_err() {
+                    continue;
+                }
+
+                procedure.push(instructions);
+
+                if search_procedure(stacks, target, crane, allowed_moves, procedure, seen) {
+                    return true;
+                }
+
+                let instructions = procedure.pop().unwrap();
+                crane.undo_move_crates(&instructions, stacks).unwrap();
+            }
+        }
+    }
+
+    false
+}
+
+// Stops at the first instruction that can't be carried out, leaving the stacks as they were before it
+fn move_crates(
+    procedure: &str,
+    stacks: &mut Stacks,
+    crane: &dyn Crane,
+    trace: bool,
+) -> Result<(), ProcedureError> {
+    run_procedure(
+        procedure.lines().enumerate(),
+        stacks,
+        trace,
+        |instructions, stacks| crane.move_crates(instructions, stacks),
+    )
+}
+
+// Undoes the procedure from its last instruction to its first, taking the final stacks back to the initial stacks
+fn undo_move_crates(
+    procedure: &str,
+    stacks: &mut Stacks,
+    crane: &dyn Crane,
+    trace: bool,
+) -> Result<(), ProcedureError> {
+    let lines: Vec<(usize, &str)> = procedure.lines().enumerate().collect();
+
+    run_procedure(
+        lines.into_iter().rev(),
+        stacks,
+        trace,
+        |instructions, stacks| crane.undo_move_crates(instructions, stacks),
+    )
+}
+
+fn run_procedure<'a>(
+    lines: impl Iterator<Item = (usize, &'a str)>,
+    stacks: &mut Stacks,
+    trace: bool,
+    step: impl Fn(&MoveInstructions, &mut Stacks) -> Result<(), CraneError>,
+) -> Result<(), ProcedureError> {
+    for (i, instructions) in lines {
+        if instructions.trim().is_empty() {
+            continue;
+        }
+
+        let procedure_error = |error: CraneError, stacks: &Stacks| ProcedureError {
+            line: i + 1,
+            instructions: instructions.trim().to_string(),
+            stacks: stacks.clone(),
+            error,
+        };
+
+        let move_instructions = instructions
+            .parse::<MoveInstructions>()
+            .map_err(|_| procedure_error(CraneError::InvalidInstructions, stacks))?;
+
+        step(&move_instructions, stacks).map_err(|e| procedure_error(e, stacks))?;
+
+        if trace {
+            println!("{}\n{}\n", instructions.trim(), stacks);
+        }
     }
 
-    println!("Top Crates in Stacks: {}", top_crates_of_stacks);
+    Ok(())
 }
 
-fn move_crates(procedure: &str, stacks: &mut Stacks) {
-    procedure.lines().for_each(|instructions| {
-        let move_instructions = MoveInstructions::new(instructions);
-        Mover::move_crates(move_instructions, stacks);
-    })
+#[derive(Debug, PartialEq)]
+enum CraneError {
+    InvalidInstructions,
+    // stack numbers start from 1, like in the instructions
+    StackOutOfRange(usize),
+    NotEnoughCrates {
+        stack: usize,
+        crates: usize,
+        amount_of_crates: usize,
+    },
 }
 
+impl fmt::Display for CraneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CraneError::InvalidInstructions => {
+                write!(f, "instructions must look like: move 1 from 2 to 1")
+            }
+            CraneError::StackOutOfRange(stack) => write!(f, "there is no stack {}", stack),
+            CraneError::NotEnoughCrates {
+                stack,
+                crates,
+                amount_of_crates,
+            } => write!(
+                f,
+                "cannot move {} crates from stack {}, it only has {}",
+                amount_of_crates, stack, crates
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct ProcedureError {
+    // counted from the first line of the procedure, not of the whole input
+    line: usize,
+    instructions: String,
+    stacks: Stacks,
+    error: CraneError,
+}
+
+impl fmt::Display for ProcedureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "procedure line {} \"{}\": {}\nstacks at that point:\n{}",
+            self.line, self.instructions, self.error, self.stacks
+        )
+    }
+}
+
+fn crane_model(model: &str) -> Option<Box<dyn Crane>> {
+    match model {
+        "9000" => Some(Box::new(CrateMover9000)),
+        "9001" => Some(Box::new(CrateMover9001)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 struct MoveInstructions {
     amount_of_crates: usize,
     from_stack_index: usize,
     to_stack_index: usize,
 }
 
-impl MoveInstructions {
-    fn new(instructions: &str) -> MoveInstructions {
+#[derive(Debug, PartialEq, Eq)]
+struct ParseMoveInstructionsError;
+
+impl FromStr for MoveInstructions {
+    type Err = ParseMoveInstructionsError;
+
+    fn from_str(instructions: &str) -> Result<Self, Self::Err> {
         // Split the instructions string by white space and alphanumerics
         let mut instructions = instructions
             .split(|c| char::is_alphabetic(c) || char::is_whitespace(c))
-            .filter(|s| !s.is_empty());
+            .filter(|s| !s.is_empty())
+            .map(|number| {
+                number
+                    .parse::<usize>()
+                    .map_err(|_| ParseMoveInstructionsError)
+            });
+
+        let mut next_number = || {
+            instructions
+                .next()
+                .unwrap_or(Err(ParseMoveInstructionsError))
+        };
 
         // first number instance in instructions (could be greater than a single digit) is amount
 
         // second number instance in instructions (could be greater than a single digit) is from stack.
 
         // third number instance in instructions (could be greater than a single digit) is to stack.
-        MoveInstructions {
-            amount_of_crates: instructions.next().unwrap().parse().unwrap(),
-            from_stack_index: instructions.next().unwrap().parse::<usize>().unwrap() - 1,
-            to_stack_index: instructions.next().unwrap().parse::<usize>().unwrap() - 1,
-        }
+        // stacks are numbered from 1, so there is no stack 0
+        Ok(MoveInstructions {
+            amount_of_crates: next_number()?,
+            from_stack_index: next_number()?
+                .checked_sub(1)
+                .ok_or(ParseMoveInstructionsError)?,
+            to_stack_index: next_number()?
+                .checked_sub(1)
+                .ok_or(ParseMoveInstructionsError)?,
+        })
+    }
+}
+
+impl fmt::Display for MoveInstructions {
+    // Same as the puzzle input: move 1 from 2 to 1
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.amount_of_crates,
+            self.from_stack_index + 1,
+            self.to_stack_index + 1
+        )
     }
 }
 
-struct Mover();
+trait Crane {
+    fn name(&self) -> &'static str;
+
+    // Stacks are left untouched if the move can't be done
+    fn move_crates(
+        &self,
+        instructions: &MoveInstructions,
+        stacks: &mut Stacks,
+    ) -> Result<(), CraneError>;
+
+    // Puts the moved crates back on the stack they came from.
+    // Moving the same amount of crates back the other way does this for both the 9000,
+    // which flips the crates it moves, and the 9001, which keeps their order.
+    fn undo_move_crates(
+        &self,
+        instructions: &MoveInstructions,
+        stacks: &mut Stacks,
+    ) -> Result<(), CraneError> {
+        let back = MoveInstructions {
+            amount_of_crates: instructions.amount_of_crates,
+            from_stack_index: instructions.to_stack_index,
+            to_stack_index: instructions.from_stack_index,
+        };
+
+        self.move_crates(&back, stacks)
+    }
+}
+
+// Moves crates one at a time
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn name(&self) -> &'static str {
+        "CrateMover 9000"
+    }
+
+    fn move_crates(
+        &self,
+        instructions: &MoveInstructions,
+        stacks: &mut Stacks,
+    ) -> Result<(), CraneError> {
+        stacks.check_move(instructions)?;
 
-impl Mover {
-    fn move_crates(instructions: MoveInstructions, stacks: &mut Stacks) {
         let amount_of_crates = instructions.amount_of_crates;
         let stacks = &mut stacks.stacks;
 
@@ -69,12 +614,43 @@ impl Mover {
             let to_stack = &mut stacks[instructions.to_stack_index];
             to_stack.push(crate_to_move);
         }
+
+        Ok(())
     }
 }
 
+// Moves multiple crates at once, so they keep their order
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn name(&self) -> &'static str {
+        "CrateMover 9001"
+    }
+
+    fn move_crates(
+        &self,
+        instructions: &MoveInstructions,
+        stacks: &mut Stacks,
+    ) -> Result<(), CraneError> {
+        stacks.check_move(instructions)?;
+
+        let stacks = &mut stacks.stacks;
+
+        let from_stack = &mut stacks[instructions.from_stack_index];
+
+        let crates_to_move = from_stack.pop_many(instructions.amount_of_crates).unwrap();
+
+        let to_stack = &mut stacks[instructions.to_stack_index];
+        to_stack.push_many(crates_to_move);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Stacks {
     count: usize,
-    stacks: Vec<Stack<char>>,
+    stacks: Vec<Stack<String>>,
 }
 
 impl Stacks {
@@ -85,45 +661,179 @@ impl Stacks {
     // [Z] [M] [P]
     //  1   2   3
     fn new(stacks: &str) -> Self {
-        let mut stacks = stacks.lines().rev();
+        // Footer is the last line that isn't blank
+        let mut stacks = stacks
+            .lines()
+            .rev()
+            .skip_while(|line| line.trim().is_empty());
+
+        let columns = Self::columns(stacks.next().unwrap_or_default());
 
-        let count: usize = stacks.next().unwrap().split_whitespace().count();
+        let count: usize = columns.len();
 
         let crates = stacks;
 
         Stacks {
             count,
-            stacks: Self::place_on_stacks(crates, count),
+            stacks: Self::place_on_stacks(crates, &columns),
         }
     }
 
+    // Character positions (start, end) of every stack number in the footer line: " 1   2   3 "
+    // A stack's crates are drawn above its number, so this is where each stack's column is.
+    fn columns(footer: &str) -> Vec<(usize, usize)> {
+        let mut columns: Vec<(usize, usize)> = Vec::new();
+        let mut start: Option<usize> = None;
+
+        for (i, c) in footer.chars().chain([' ']).enumerate() {
+            match (c.is_whitespace(), start) {
+                (false, None) => start = Some(i),
+                (true, Some(column_start)) => {
+                    columns.push((column_start, i - 1));
+                    start = None;
+                }
+                _ => (),
+            }
+        }
+
+        columns
+    }
+
+    // A crate is everything between [ and ], labels can be any length: [Z] [10] [#]
+    // Gives the start and end character positions of every crate in the row, and its label.
+    fn crates_in_row(row: &str) -> Vec<(usize, usize, String)> {
+        let mut crates: Vec<(usize, usize, String)> = Vec::new();
+        let mut current: Option<(usize, String)> = None;
+
+        for (i, c) in row.chars().enumerate() {
+            match (c, current.as_mut()) {
+                ('[', None) => current = Some((i, String::new())),
+                (']', Some(_)) => {
+                    let (start, supplies) = current.take().unwrap();
+                    crates.push((start, i, supplies));
+                }
+                (c, Some((_, supplies))) => supplies.push(c),
+                (_, None) => (),
+            }
+        }
+
+        crates
+    }
+
     fn place_on_stacks<'a>(
         crates: impl Iterator<Item = &'a str>,
-        count: usize,
-    ) -> Vec<Stack<char>> {
+        columns: &[(usize, usize)],
+    ) -> Vec<Stack<String>> {
         // Getting Supplies in the Crates of the Stacks
 
         // Initialize new Vector of Stacks
-        let mut stacks: Vec<Stack<char>> = (0..count).map(|_| Stack::new()).collect();
+        let mut stacks: Vec<Stack<String>> = (0..columns.len()).map(|_| Stack::new()).collect();
+
+        if columns.is_empty() {
+            return stacks;
+        }
 
         // Parsing Each row of string crates to place into stacks
         crates.for_each(|crates| {
-            crates
-                .chars()
-                .skip(1) // Skip first index of characters in OG String which is either a beginning of crate: [ or empty. Next iterated value will be supplies if crate or empty if empty.
-                .enumerate() // Enumerate the Characters starting index @ 0 from the first possible supplies.
-                .filter(|(i, supplies)| supplies.is_alphabetic() && i % 4 == 0) // Filter only possible supplies.
-                // Starting from an index where supplies possibly are, the next supplies character will be 4 indices ahead.
-                // ex: [S1] [S2] S1 to S2 is index 0 (starting from first possible supply) to index 4.
-                .map(|(i, supplies)| (i / 4, supplies)) // Map OG String Indices to Designated Stacks Indices
-                // Since supplies go in stacks left to right, the Character enumeration index for a supply is % 4, and the String supplies index is 4x the designated stack index, the mapped designated stacks index is just i / 4.
-                .for_each(|(i, supplies)| stacks[i].push(supplies));
+            let crates = Self::crates_in_row(crates);
+
+            for (start, end, supplies) in crates {
+                // The crate goes on the stack whose number is closest to the middle of the crate
+                // (positions are doubled so middles stay whole numbers)
+                let middle = (start + end) as i64;
+
+                let i = (0..columns.len())
+                    .min_by_key(|i| ((columns[*i].0 + columns[*i].1) as i64 - middle).abs())
+                    .unwrap();
+
+                stacks[i].push(supplies);
+            }
         });
 
         stacks
     }
+
+    // Both stacks exist and there are enough crates to move
+    fn check_move(&self, instructions: &MoveInstructions) -> Result<(), CraneError> {
+        for index in [instructions.from_stack_index, instructions.to_stack_index] {
+            if index >= self.count {
+                return Err(CraneError::StackOutOfRange(index + 1));
+            }
+        }
+
+        let crates = self.stacks[instructions.from_stack_index].length();
+
+        if instructions.amount_of_crates > crates {
+            return Err(CraneError::NotEnoughCrates {
+                stack: instructions.from_stack_index + 1,
+                crates,
+                amount_of_crates: instructions.amount_of_crates,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Top crate of every stack, skipping empty stacks
+    fn top_crates(&self) -> String {
+        self.stacks
+            .iter()
+            .filter_map(|stack| stack.peek())
+            .map(|supplies| supplies.as_str())
+            .collect()
+    }
+}
+
+impl fmt::Display for Stacks {
+    // Same diagram as the puzzle input, highest crates first:
+    //     [D]
+    // [N] [C]
+    // [Z] [M] [P]
+    //  1   2   3
+    // Columns are widened to fit longer crate labels and stack numbers.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let widths: Vec<usize> = self
+            .stacks
+            .iter()
+            .enumerate()
+            .map(|(i, stack)| {
+                let label_width = (0..stack.length())
+                    .filter_map(|level| stack.get(level))
+                    .map(|supplies| supplies.chars().count())
+                    .max()
+                    .unwrap_or(1);
+
+                label_width.max((i + 1).to_string().len()) + 2
+            })
+            .collect();
+
+        let height = self.stacks.iter().map(|stack| stack.length()).max();
+
+        for level in (0..height.unwrap_or(0)).rev() {
+            let row: Vec<String> = self
+                .stacks
+                .iter()
+                .zip(widths.iter())
+                .map(|(stack, width)| match stack.get(level) {
+                    Some(supplies) => format!("{:^width$}", format!("[{}]", supplies)),
+                    None => " ".repeat(*width),
+                })
+                .collect();
+
+            writeln!(f, "{}", row.join(" "))?;
+        }
+
+        let footer: Vec<String> = widths
+            .iter()
+            .enumerate()
+            .map(|(i, width)| format!("{:^width$}", i + 1))
+            .collect();
+
+        write!(f, "{}", footer.join(" "))
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Stack<T> {
     stack: Vec<T>,
 }
@@ -146,6 +856,20 @@ impl<T> Stack<T> {
         self.stack.push(item)
     }
 
+    // Takes the top amount of items off, bottom item first
+    fn pop_many(&mut self, amount: usize) -> Option<Vec<T>> {
+        if amount > self.stack.len() {
+            return None;
+        }
+
+        Some(self.stack.split_off(self.stack.len() - amount))
+    }
+
+    // Puts items on top, keeping their order
+    fn push_many(&mut self, items: Vec<T>) {
+        self.stack.extend(items)
+    }
+
     fn is_empty(&self) -> bool {
         self.stack.is_empty()
     }
@@ -153,4 +877,384 @@ impl<T> Stack<T> {
     fn peek(&self) -> Option<&T> {
         self.stack.last()
     }
+
+    // Item at a level of the stack, 0 being the bottom
+    fn get(&self, level: usize) -> Option<&T> {
+        self.stack.get(level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code_2022::XorShift;
+
+    const EXAMPLE_STACKS: &str = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 ";
+
+    const EXAMPLE_PROCEDURE: &str = "move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2";
+
+    fn rearrange(crane: &dyn Crane) -> Stacks {
+        let mut stacks = Stacks::new(EXAMPLE_STACKS);
+
+        move_crates(EXAMPLE_PROCEDURE, &mut stacks, crane, false).unwrap();
+
+        stacks
+    }
+
+    #[test]
+    fn example_crate_mover_9000() {
+        assert_eq!(rearrange(&CrateMover9000).top_crates(), "CMZ");
+    }
+
+    #[test]
+    fn example_crate_mover_9001() {
+        assert_eq!(rearrange(&CrateMover9001).top_crates(), "MCD");
+    }
+
+    #[test]
+    fn crane_models_at_runtime() {
+        assert_eq!(crane_model("9000").unwrap().name(), "CrateMover 9000");
+        assert_eq!(crane_model("9001").unwrap().name(), "CrateMover 9001");
+        assert!(crane_model("9002").is_none());
+    }
+
+    #[test]
+    fn display_example_stacks() {
+        assert_eq!(Stacks::new(EXAMPLE_STACKS).to_string(), EXAMPLE_STACKS);
+    }
+
+    #[test]
+    fn display_round_trip() {
+        let stacks = rearrange(&CrateMover9000);
+
+        let diagram = stacks.to_string();
+
+        assert_eq!(
+            diagram,
+            "        [Z]
+        [N]
+        [D]
+[C] [M] [P]
+ 1   2   3 "
+        );
+        assert_eq!(Stacks::new(&diagram), stacks);
+    }
+
+    #[test]
+    fn more_than_nine_stacks() {
+        let diagram = "[A]                                     [K]
+[B] [C] [D] [E] [F] [G] [H] [I] [J] [L]  [M]
+ 1   2   3   4   5   6   7   8   9   10   11";
+
+        let stacks = Stacks::new(diagram);
+
+        assert_eq!(stacks.count, 11);
+        assert_eq!(stacks.top_crates(), "ACDEFGHIJLK");
+        assert_eq!(Stacks::new(&stacks.to_string()), stacks);
+    }
+
+    #[test]
+    fn ragged_trailing_whitespace() {
+        let diagram = "    [D]
+[N] [C]          \r
+[Z] [M] [P]
+ 1   2   3 \r
+";
+
+        assert_eq!(Stacks::new(diagram), Stacks::new(EXAMPLE_STACKS));
+    }
+
+    #[test]
+    fn multi_character_crate_labels() {
+        let diagram = "     [#]
+[12] [AB] [z]
+ 1    2    3";
+
+        let mut stacks = Stacks::new(diagram);
+
+        assert_eq!(stacks.top_crates(), "12#z");
+
+        move_crates("move 2 from 2 to 3", &mut stacks, &CrateMover9001, false).unwrap();
+
+        assert_eq!(stacks.top_crates(), "12#");
+        assert_eq!(Stacks::new(&stacks.to_string()), stacks);
+    }
+
+    #[test]
+    fn parse_move_instructions() {
+        assert_eq!(
+            "move 12 from 2 to 10".parse::<MoveInstructions>(),
+            Ok(MoveInstructions {
+                amount_of_crates: 12,
+                from_stack_index: 1,
+                to_stack_index: 9,
+            })
+        );
+        assert!("move 1 from 0 to 1".parse::<MoveInstructions>().is_err());
+        assert!("move 1 from 2".parse::<MoveInstructions>().is_err());
+        assert!("move one from 2 to 1".parse::<MoveInstructions>().is_err());
+    }
+
+    #[test]
+    fn not_enough_crates_names_the_line() {
+        let mut stacks = Stacks::new(EXAMPLE_STACKS);
+
+        let procedure = "move 1 from 2 to 1\nmove 3 from 3 to 1";
+
+        let error = move_crates(procedure, &mut stacks, &CrateMover9000, false).unwrap_err();
+
+        let mut expected_stacks = Stacks::new(EXAMPLE_STACKS);
+        move_crates(
+            "move 1 from 2 to 1",
+            &mut expected_stacks,
+            &CrateMover9000,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(error.line, 2);
+        assert_eq!(error.instructions, "move 3 from 3 to 1");
+        assert_eq!(
+            error.error,
+            CraneError::NotEnoughCrates {
+                stack: 3,
+                crates: 1,
+                amount_of_crates: 3
+            }
+        );
+        assert_eq!(error.stacks, expected_stacks);
+        assert_eq!(stacks, expected_stacks);
+    }
+
+    #[test]
+    fn stack_out_of_range() {
+        let mut stacks = Stacks::new(EXAMPLE_STACKS);
+
+        let error =
+            move_crates("move 1 from 1 to 4", &mut stacks, &CrateMover9001, false).unwrap_err();
+
+        assert_eq!(error.error, CraneError::StackOutOfRange(4));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "procedure line 1 \"move 1 from 1 to 4\": there is no stack 4\nstacks at that point:\n{}",
+                EXAMPLE_STACKS
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_instructions() {
+        let mut stacks = Stacks::new(EXAMPLE_STACKS);
+
+        let error = move_crates("move 1 from 1", &mut stacks, &CrateMover9000, false).unwrap_err();
+
+        assert_eq!(error.error, CraneError::InvalidInstructions);
+    }
+
+    #[test]
+    fn undo_example_procedure() {
+        for crane in [&CrateMover9000 as &dyn Crane, &CrateMover9001] {
+            let mut stacks = rearrange(crane);
+
+            undo_move_crates(EXAMPLE_PROCEDURE, &mut stacks, crane, false).unwrap();
+
+            assert_eq!(stacks, Stacks::new(EXAMPLE_STACKS));
+        }
+    }
+
+    #[test]
+    fn undo_names_the_failing_line() {
+        // Undoing line 2, "move 3 from 1 to 3", needs 3 crates on stack 3
+        let mut stacks = Stacks::new(EXAMPLE_STACKS);
+
+        let error =
+            undo_move_crates(EXAMPLE_PROCEDURE, &mut stacks, &CrateMover9000, false).unwrap_err();
+
+        assert_eq!(error.line, 2);
+    }
+
+    // Random stacks and procedures that can always be carried out:
+    // moving forwards then backwards must give back the initial stacks.
+    #[test]
+    fn forward_then_backward_is_identity() {
+        let mut random = XorShift::new(0x2022_0005);
+
+        for case in 0..200 {
+            let count = random.below(6) + 1;
+
+            let mut initial_stacks = Stacks {
+                count,
+                stacks: (0..count).map(|_| Stack::new()).collect(),
+            };
+
+            for i in 0..random.below(20) {
+                initial_stacks.stacks[random.below(count)].push(i.to_string());
+            }
+
+            let crane: &dyn Crane = if case % 2 == 0 {
+                &CrateMover9000
+            } else {
+                &CrateMover9001
+            };
+
+            let mut stacks = initial_stacks.clone();
+            let mut procedure: Vec<String> = Vec::new();
+
+            for _ in 0..random.below(15) {
+                let from = random.below(count);
+                let to = random.below(count);
+                let amount = random.below(stacks.stacks[from].length() + 1);
+
+                let instructions = format!("move {} from {} to {}", amount, from + 1, to + 1);
+
+                move_crates(&instructions, &mut stacks, crane, false).unwrap();
+                procedure.push(instructions);
+            }
+
+            undo_move_crates(&procedure.join("\n"), &mut stacks, crane, false).unwrap();
+
+            assert_eq!(stacks, initial_stacks);
+        }
+    }
+
+    #[test]
+    fn move_instructions_round_trip() {
+        let instructions: MoveInstructions = "move 3 from 1 to 3".parse().unwrap();
+
+        assert_eq!(instructions.to_string(), "move 3 from 1 to 3");
+    }
+
+    #[test]
+    fn plan_to_example_top_crates() {
+        for (crane, top_crates) in [
+            (&CrateMover9000 as &dyn Crane, "CMZ"),
+            (&CrateMover9001, "MCD"),
+        ] {
+            let stacks = Stacks::new(EXAMPLE_STACKS);
+            let target = Target::TopCrates(top_crates.to_string());
+
+            let procedure = plan_procedure(&stacks, &target, crane, 4).unwrap();
+
+            // The example takes 4 moves, a plan can't be any longer
+            assert!(procedure.len() <= 4);
+
+            let procedure: Vec<String> = procedure.iter().map(|i| i.to_string()).collect();
+
+            let mut planned_stacks = stacks.clone();
+            move_crates(&procedure.join("\n"), &mut planned_stacks, crane, false).unwrap();
+
+            assert_eq!(planned_stacks.top_crates(), top_crates);
+        }
+    }
+
+    #[test]
+    fn plan_to_example_final_stacks() {
+        let stacks = Stacks::new(EXAMPLE_STACKS);
+        let target = Target::Stacks(rearrange(&CrateMover9001));
+
+        let procedure = plan_procedure(&stacks, &target, &CrateMover9001, 4).unwrap();
+
+        let procedure: Vec<String> = procedure.iter().map(|i| i.to_string()).collect();
+
+        let mut planned_stacks = stacks.clone();
+        move_crates(
+            &procedure.join("\n"),
+            &mut planned_stacks,
+            &CrateMover9001,
+            false,
+        )
+        .unwrap();
+
+        assert!(target.reached(&planned_stacks));
+    }
+
+    #[test]
+    fn plan_to_stacks_from_diagram() {
+        let input = format!("{}\n\n{}", rearrange(&CrateMover9000), EXAMPLE_PROCEDURE);
+        let target = Target::Stacks(target_stacks(&input));
+
+        assert!(target.reached(&rearrange(&CrateMover9000)));
+
+        let stacks = Stacks::new(EXAMPLE_STACKS);
+        let procedure = plan_procedure(&stacks, &target, &CrateMover9000, 4).unwrap();
+
+        assert!(procedure.len() <= 4);
+    }
+
+    #[test]
+    fn plan_unreachable_target() {
+        let stacks = Stacks::new(EXAMPLE_STACKS);
+        let target = Target::TopCrates("XYZ".to_string());
+
+        assert!(plan_procedure(&stacks, &target, &CrateMover9000, 3).is_none());
+    }
+
+    #[test]
+    fn plan_already_at_target() {
+        let stacks = Stacks::new(EXAMPLE_STACKS);
+        let target = Target::TopCrates("NDP".to_string());
+
+        assert_eq!(
+            plan_procedure(&stacks, &target, &CrateMover9000, 3),
+            Some(vec![])
+        );
+    }
+
+    // 9 stacks of 4 crates, A to D on the first stack (D on top), E to H on the second and so on
+    fn nine_stacks() -> Stacks {
+        let labels: Vec<char> = ('A'..='Z').chain('a'..='j').collect();
+
+        let rows: Vec<String> = (0..4)
+            .rev()
+            .map(|level| {
+                let row: Vec<String> = (0..9)
+                    .map(|stack| format!("[{}]", labels[stack * 4 + level]))
+                    .collect();
+                row.join(" ")
+            })
+            .collect();
+
+        Stacks::new(&format!(
+            "{}\n 1   2   3   4   5   6   7   8   9 ",
+            rows.join("\n")
+        ))
+    }
+
+    // Planning on a full size diagram has to finish, whether or not the target can be reached
+    #[test]
+    fn plan_on_nine_stacks() {
+        let stacks = nine_stacks();
+        assert_eq!(stacks.top_crates(), "DHLPTXbfj");
+
+        for crane in [&CrateMover9000 as &dyn Crane, &CrateMover9001] {
+            // the crates on top of the first two stacks swapped
+            let target = Target::TopCrates("HDLPTXbfj".to_string());
+            let procedure = plan_procedure(&stacks, &target, crane, MAX_PLANNED_MOVES).unwrap();
+            assert_eq!(procedure.len(), 3);
+
+            // uncovering the crate under any top crate always covers another stack's
+            let target = Target::TopCrates("CGKOSWaei".to_string());
+            assert!(plan_procedure(&stacks, &target, crane, MAX_PLANNED_MOVES).is_none());
+
+            // takes 4 moves
+            let target = Target::TopCrates("ARLTQUbfj".to_string());
+            let procedure = plan_procedure(&stacks, &target, crane, MAX_PLANNED_MOVES);
+            assert!(procedure.is_some_and(|procedure| procedure.len() <= 4));
+
+            // reachable, in more moves than that
+            let target = Target::TopCrates("DFGfQVYbH".to_string());
+            assert!(plan_procedure(&stacks, &target, crane, MAX_PLANNED_MOVES).is_none());
+
+            // there is no crate Z
+            let target = Target::TopCrates("ZZZZZZZZZ".to_string());
+            assert!(plan_procedure(&stacks, &target, crane, MAX_PLANNED_MOVES).is_none());
+        }
+    }
 }
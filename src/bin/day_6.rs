@@ -1,42 +1,465 @@
+use std::collections::HashMap;
+#[cfg(test)]
 use std::collections::HashSet;
 use std::env;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{self, Read};
 use std::process;
 
 fn main() {
-    // Read args
+    // Read args: the data stream is read from the file in the first arg, or from stdin if there is no arg or it is -
+    // --frames before the file decodes the whole stream into packets and messages
 
-    let mut data_stream = env::args();
+    let mut args = env::args().peekable();
 
-    data_stream.next();
+    args.next();
 
-    let data_stream = match data_stream.next() {
-        Some(arg) => arg,
-        None => {
-            eprintln!("No Data Stream, first arg ought to be a data stream");
+    let frames = args.next_if(|arg| arg == "--frames").is_some();
+
+    let mut data_stream: Box<dyn Read> = match args.next().as_deref() {
+        None | Some("-") => Box::new(io::stdin()),
+        Some(path) => match File::open(path) {
+            Ok(file) => Box::new(file),
+            Err(e) => {
+                eprintln!("Cannot open data stream {}: {}", path, e);
+                process::exit(1);
+            }
+        },
+    };
+
+    if frames {
+        let mut whole_stream = Vec::new();
+
+        if let Err(e) = data_stream.read_to_end(&mut whole_stream) {
+            eprintln!("Cannot read data stream: {}", e);
+            process::exit(1);
+        }
+
+        // the data stream ends at the first line break, like it does for find_markers_in_stream
+        let end = whole_stream
+            .iter()
+            .position(|byte| *byte == b'\n' || *byte == b'\r')
+            .unwrap_or(whole_stream.len());
+        let whole_stream = &whole_stream[..end];
+
+        for (name, marker_length) in [
+            ("packet", START_OF_PACKET_MARKER_LENGTH),
+            ("message", START_OF_MESSAGE_MARKER_LENGTH),
+        ] {
+            for frame in Frames::new(whole_stream, marker_length) {
+                println!(
+                    "{} at {} (marker at {}): {}",
+                    name,
+                    frame.offset,
+                    frame.marker_offset,
+                    String::from_utf8_lossy(frame.payload)
+                );
+            }
+        }
+
+        return;
+    }
+
+    let marker_lengths = [
+        START_OF_PACKET_MARKER_LENGTH,
+        START_OF_MESSAGE_MARKER_LENGTH,
+    ];
+
+    let markers = match find_markers_in_stream(data_stream, &marker_lengths, CHUNK_SIZE) {
+        Ok(markers) => markers,
+        Err(e) => {
+            eprintln!("Cannot read data stream: {}", e);
             process::exit(1);
         }
     };
 
-    for i in 0..data_stream.len() - 4 {
-        if unique(&data_stream[i..i + 3 + 1]) {
-            println!("{}: {}", &data_stream[i..i + 3 + 1], i + 3 + 1);
-            break;
+    for (name, marker) in ["start-of-packet", "start-of-message"].iter().zip(markers) {
+        match marker {
+            Some(offset) => println!("{} marker: {}", name, offset),
+            None => println!("{} marker: not found", name),
+        }
+    }
+}
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// Reads the data stream a chunk at a time, looking for a marker of every length at once.
+// Gives the byte offset just past each marker, the detectors carry on across chunk boundaries.
+// The data stream ends at the end of the input or the first line break.
+fn find_markers_in_stream(
+    mut data_stream: impl Read,
+    marker_lengths: &[usize],
+    chunk_size: usize,
+) -> io::Result<Vec<Option<usize>>> {
+    let mut detectors: Vec<MarkerDetector<u8>> = marker_lengths
+        .iter()
+        .map(|marker_length| MarkerDetector::new(*marker_length))
+        .collect();
+
+    let mut markers: Vec<Option<usize>> = marker_lengths
+        .iter()
+        .map(|marker_length| if *marker_length == 0 { Some(0) } else { None })
+        .collect();
+
+    let mut chunk = vec![0; chunk_size];
+    let mut offset = 0;
+
+    while markers.iter().any(Option::is_none) {
+        let read = match data_stream.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        for byte in &chunk[..read] {
+            if *byte == b'\n' || *byte == b'\r' {
+                return Ok(markers);
+            }
+
+            offset += 1;
+
+            for (detector, marker) in detectors.iter_mut().zip(markers.iter_mut()) {
+                if detector.push(*byte) && marker.is_none() {
+                    *marker = Some(offset);
+                }
+            }
+        }
+    }
+
+    Ok(markers)
+}
+
+const START_OF_PACKET_MARKER_LENGTH: usize = 4;
+
+const START_OF_MESSAGE_MARKER_LENGTH: usize = 14;
+
+// A packet or message: everything after its marker, up to the next marker or the end of the stream
+#[derive(Debug, PartialEq)]
+struct Frame<'a> {
+    // byte offsets of the start of the marker and the start of the payload
+    marker_offset: usize,
+    offset: usize,
+    payload: &'a [u8],
+}
+
+// Splits a data stream into frames. Marker detection starts over after every marker,
+// so the next marker is the first marker_length different bytes in a row after it.
+struct Frames<'a> {
+    data_stream: &'a [u8],
+    marker_length: usize,
+    // where to start looking for the next marker
+    position: usize,
+}
+
+impl<'a> Frames<'a> {
+    fn new(data_stream: &'a [u8], marker_length: usize) -> Frames<'a> {
+        Frames {
+            data_stream,
+            marker_length,
+            position: 0,
+        }
+    }
+
+    // Byte offsets of the start and end of the first marker from start
+    fn find_marker_from(&self, start: usize) -> Option<(usize, usize)> {
+        let marker_end = start + find_marker(&self.data_stream[start..], self.marker_length)?;
+
+        Some((marker_end - self.marker_length, marker_end))
+    }
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = Frame<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // a marker of no bytes would never move the position along
+        if self.marker_length == 0 {
+            return None;
+        }
+
+        let (marker_offset, offset) = self.find_marker_from(self.position)?;
+
+        let end = match self.find_marker_from(offset) {
+            Some((next_marker_offset, _)) => next_marker_offset,
+            None => self.data_stream.len(),
+        };
+
+        self.position = end;
+
+        Some(Frame {
+            marker_offset,
+            offset,
+            payload: &self.data_stream[offset..end],
+        })
+    }
+}
+
+// Number of bytes processed once the first marker_length bytes in a row that are all different have been read.
+// Bytes rather than characters, so the offsets are the same as find_markers_in_stream's.
+fn find_marker(data_stream: &[u8], marker_length: usize) -> Option<usize> {
+    if marker_length == 0 {
+        return Some(0);
+    }
+
+    let mut detector = MarkerDetector::new(marker_length);
+
+    data_stream
+        .iter()
+        .position(|byte| detector.push(*byte))
+        .map(|i| i + 1)
+}
+
+// Finds markers one item at a time, in a single pass over the stream.
+// Remembers where each item was last seen, so a repeat inside the window
+// moves the window start past the earlier copy instead of rechecking the whole window.
+struct MarkerDetector<T> {
+    marker_length: usize,
+    last_seen: HashMap<T, usize>,
+    window_start: usize,
+    position: usize,
+}
+
+impl<T: Hash + Eq> MarkerDetector<T> {
+    fn new(marker_length: usize) -> MarkerDetector<T> {
+        MarkerDetector {
+            marker_length,
+            last_seen: HashMap::new(),
+            window_start: 0,
+            position: 0,
         }
     }
+
+    // true when this item and the ones before it make a marker
+    fn push(&mut self, item: T) -> bool {
+        if let Some(last_seen) = self.last_seen.insert(item, self.position) {
+            if last_seen >= self.window_start {
+                self.window_start = last_seen + 1;
+            }
+        }
+
+        self.position += 1;
+
+        self.position - self.window_start >= self.marker_length
+    }
 }
 
-fn unique(stream: &str) -> bool {
-    let mut chars: HashSet<char> = HashSet::new();
+// Same as find_marker, checking every window with unique. O(n * marker_length).
+// Kept as the reference find_marker is tested against.
+#[cfg(test)]
+fn find_marker_by_windows(data_stream: &[u8], marker_length: usize) -> Option<usize> {
+    if marker_length == 0 {
+        return Some(0);
+    }
+
+    data_stream
+        .windows(marker_length)
+        .position(unique)
+        .map(|i| i + marker_length)
+}
+
+#[cfg(test)]
+fn unique(stream: &[u8]) -> bool {
+    let mut bytes: HashSet<u8> = HashSet::new();
+
+    stream.iter().all(|byte| bytes.insert(*byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code_2022::XorShift;
+
+    // data stream, start-of-packet marker, start-of-message marker
+    const EXAMPLE_STREAMS: [(&str, usize, usize); 5] = [
+        ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 7, 19),
+        ("bvwbjplbgvbhsrlpgdmjqwftvncz", 5, 23),
+        ("nppdvjthqldpwncqszvftbrmjlhg", 6, 23),
+        ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 10, 29),
+        ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 11, 26),
+    ];
+
+    #[test]
+    fn example_start_of_packet_markers() {
+        for (data_stream, start_of_packet, _) in EXAMPLE_STREAMS {
+            assert_eq!(
+                find_marker(data_stream.as_bytes(), START_OF_PACKET_MARKER_LENGTH),
+                Some(start_of_packet)
+            );
+        }
+    }
+
+    #[test]
+    fn example_start_of_message_markers() {
+        for (data_stream, _, start_of_message) in EXAMPLE_STREAMS {
+            assert_eq!(
+                find_marker(data_stream.as_bytes(), START_OF_MESSAGE_MARKER_LENGTH),
+                Some(start_of_message)
+            );
+        }
+    }
+
+    #[test]
+    fn marker_in_last_window() {
+        assert_eq!(find_marker(b"aaabcd", 4), Some(6));
+    }
+
+    #[test]
+    fn no_marker() {
+        assert_eq!(find_marker(b"aaaaaaaa", 4), None);
+        assert_eq!(find_marker(b"abc", 4), None);
+        assert_eq!(find_marker(b"", 4), None);
+    }
+
+    #[test]
+    fn marker_detector_moves_window_past_repeats() {
+        let mut detector = MarkerDetector::new(3);
+
+        let found: Vec<bool> = "abacd".chars().map(|c| detector.push(c)).collect();
+
+        assert_eq!(found, vec![false, false, false, true, true]);
+    }
+
+    // find_marker and find_marker_by_windows agree on random streams
+    #[test]
+    fn sliding_window_matches_unique_windows() {
+        let mut random = XorShift::new(0x2022_0006);
+
+        for _ in 0..500 {
+            // small alphabets make repeats, and so late or missing markers, likely
+            let alphabet = random.below(20) + 1;
+            let length = random.below(60);
 
-    let mut stream = stream.chars();
+            let data_stream: String = (0..length)
+                .map(|_| (b'a' + random.below(alphabet) as u8) as char)
+                .collect();
 
-    while let Some(c) = stream.next() {
-        if chars.contains(&c) {
-            return false;
+            for marker_length in 0..16 {
+                assert_eq!(
+                    find_marker(data_stream.as_bytes(), marker_length),
+                    find_marker_by_windows(data_stream.as_bytes(), marker_length),
+                    "{} with marker length {}",
+                    data_stream,
+                    marker_length
+                );
+            }
         }
+    }
+
+    #[test]
+    fn stream_markers_across_chunk_boundaries() {
+        let marker_lengths = [
+            START_OF_PACKET_MARKER_LENGTH,
+            START_OF_MESSAGE_MARKER_LENGTH,
+        ];
+
+        for (data_stream, start_of_packet, start_of_message) in EXAMPLE_STREAMS {
+            for chunk_size in 1..=8 {
+                let markers =
+                    find_markers_in_stream(data_stream.as_bytes(), &marker_lengths, chunk_size)
+                        .unwrap();
+
+                assert_eq!(markers, vec![Some(start_of_packet), Some(start_of_message)]);
+            }
+        }
+    }
 
-        chars.insert(c);
+    #[test]
+    fn stream_ends_at_line_break() {
+        let markers = find_markers_in_stream("abc\ndefg".as_bytes(), &[4], 3).unwrap();
+
+        assert_eq!(markers, vec![None]);
     }
 
-    true
+    #[test]
+    fn stream_offsets_are_bytes() {
+        let markers = find_markers_in_stream("ééab".as_bytes(), &[3], 2).unwrap();
+
+        // é is two bytes, the first 3 different bytes are the second é and a
+        assert_eq!(markers, vec![Some(5)]);
+    }
+
+    #[test]
+    fn frames_after_markers() {
+        // markers: abc, cde, then efg right at the end
+        let data_stream = "aaabccccdeeeefg";
+
+        let frames: Vec<Frame> = Frames::new(data_stream.as_bytes(), 3).collect();
+
+        assert_eq!(
+            frames,
+            vec![
+                Frame {
+                    marker_offset: 2,
+                    offset: 5,
+                    payload: b"cc",
+                },
+                Frame {
+                    marker_offset: 7,
+                    offset: 10,
+                    payload: b"ee",
+                },
+                Frame {
+                    marker_offset: 12,
+                    offset: 15,
+                    payload: b"",
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn frames_payload_runs_to_end_of_stream() {
+        let frames: Vec<Frame> = Frames::new(b"abcaaa", 3).collect();
+
+        assert_eq!(
+            frames,
+            vec![Frame {
+                marker_offset: 0,
+                offset: 3,
+                payload: b"aaa",
+            }]
+        );
+    }
+
+    #[test]
+    fn no_frames_without_marker() {
+        assert_eq!(Frames::new(b"aabbaabb", 4).count(), 0);
+        assert_eq!(Frames::new(b"", 4).count(), 0);
+        assert_eq!(Frames::new(b"abcd", 0).count(), 0);
+    }
+
+    #[test]
+    fn first_frame_starts_at_example_markers() {
+        for (data_stream, start_of_packet, start_of_message) in EXAMPLE_STREAMS {
+            let packet = Frames::new(data_stream.as_bytes(), START_OF_PACKET_MARKER_LENGTH).next();
+            let message =
+                Frames::new(data_stream.as_bytes(), START_OF_MESSAGE_MARKER_LENGTH).next();
+
+            assert_eq!(packet.unwrap().offset, start_of_packet);
+            assert_eq!(message.unwrap().offset, start_of_message);
+        }
+    }
+
+    // the same offsets as find_markers_in_stream, whatever the bytes are
+    #[test]
+    fn frames_and_stream_agree_on_offsets() {
+        let data_stream = "ééabcd".as_bytes();
+
+        let markers = find_markers_in_stream(data_stream, &[4], 2).unwrap();
+        let frame = Frames::new(data_stream, 4).next().unwrap();
+
+        assert_eq!(markers, vec![Some(frame.offset)]);
+        assert_eq!(frame.marker_offset, frame.offset - 4);
+
+        // not valid UTF-8
+        let data_stream = [0xff, 0xfe, 0xff, b'a', b'b', b'c'];
+
+        let markers = find_markers_in_stream(&data_stream[..], &[3], 4).unwrap();
+        let frame = Frames::new(&data_stream, 3).next().unwrap();
+
+        assert_eq!(markers, vec![Some(frame.offset)]);
+        assert_eq!(frame.payload, b"bc");
+    }
 }
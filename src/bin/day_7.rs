@@ -1,6 +1,6 @@
-use std::{fs, iter::Peekable};
+use std::{cmp::Reverse, env, fs, iter::Peekable, process};
 use my_tree::Node;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 
 
 fn main() {
@@ -11,15 +11,101 @@ fn main() {
 
     // ls: will read list of items after ls command in terminal output until end of file or new command is reached
 
+    // --tree prints the reconstructed filesystem in the puzzle's - / (dir) style.
+    // --du prints du -h style sizes of every directory, --sort name|size orders directories within their parent
+    // and --max-depth <depth> only prints directories up to that depth below /. --all also prints every file by its path.
+    let mut args = env::args().skip(1);
+
+    let mut tree = false;
+    let mut du = false;
+    let mut du_options = DuOptions { sort: DuSort::Name, max_depth: None, all: false };
+    // the last option given that only changes the du rendering
+    let mut du_only_option: Option<String> = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tree" => tree = true,
+            "--du" => du = true,
+            "--sort" => du_options.sort = match args.next().as_deref() {
+                Some("name") => DuSort::Name,
+                Some("size") => DuSort::Size,
+                _ => {
+                    eprintln!("--sort must be name or size");
+                    process::exit(1);
+                }
+            },
+            "--all" => du_options.all = true,
+            "--max-depth" => du_options.max_depth = match args.next().map(|depth| depth.parse::<usize>()) {
+                Some(Ok(depth)) => Some(depth),
+                _ => {
+                    eprintln!("--max-depth must be a number");
+                    process::exit(1);
+                }
+            },
+            _ => {
+                eprintln!("unknown argument: {}", arg);
+                process::exit(1);
+            }
+        }
+
+        if let "--sort" | "--all" | "--max-depth" = arg.as_str() {
+            du_only_option = Some(arg);
+        }
+    }
+
+    if tree && du {
+        eprintln!("--tree and --du can't be used together");
+        process::exit(1);
+    }
+
+    if let (false, Some(option)) = (du, &du_only_option) {
+        eprintln!("{} can only be used with --du", option);
+        process::exit(1);
+    }
+
+    let render = if tree {
+        Some(Render::Tree)
+    } else if du {
+        Some(Render::Du(du_options))
+    } else {
+        None
+    };
+
     let mut terminal_output: Peekable<std::str::Lines> = terminal_output.lines().peekable();
 
-    CommandExecutor::start(&mut terminal_output);
+    CommandExecutor::start(&mut terminal_output, render);
 }
 
 struct CommandExecutor {}
 
 impl CommandExecutor {
-    fn start(terminal_output: &mut Peekable<std::str::Lines>) {
+    fn start(terminal_output: &mut Peekable<std::str::Lines>, render: Option<Render>) {
+        match Self::build_filesystem(terminal_output) {
+            Some (mut root_directory) => {
+                calculate_filesystem_tree_total_sizes( &mut root_directory);
+
+                match render {
+                    Some(Render::Tree) => return println!("{}", render_tree(&root_directory)),
+                    Some(Render::Du(du_options)) => return println!("{}", render_du(&root_directory, du_options)),
+                    None => (),
+                }
+
+                let mut total_size = 0;
+                total_size_of_dirs_to_delete(&root_directory, &mut total_size);
+
+                println!("Total Size of Dirs to delete: {}", total_size);
+
+                match smallest_dir_to_delete(&root_directory, DISK_CAPACITY, REQUIRED_FREE_SPACE) {
+                    Some((path, size)) => println!("Smallest Dir to delete: {} {}", path, size),
+                    None => println!("No Dir frees up enough space"),
+                }
+            },
+            None => (),
+        }
+    }
+
+    // Runs every command in the terminal output, returning the root directory of the filesystem it explored
+    fn build_filesystem(terminal_output: &mut Peekable<std::str::Lines>) -> Option<Rc<Node<Dir>>> {
         let mut root_directory: Option<Rc<Node<Dir>>> = None;
 
         let mut current_directory: Option<Rc<Node<Dir>>> = None;
@@ -35,28 +121,19 @@ impl CommandExecutor {
             }
         }
 
-        match root_directory {
-            Some (mut root_directory) => {
-                calculate_filesystem_tree_total_sizes( &mut root_directory);
-                let mut total_size = 0;
-                total_size_of_dirs_to_delete(&root_directory, &mut total_size);
-
-                println!("Total Size of Dirs to delete: {}", total_size);
-            },
-            None => (),
-        }
+        root_directory
     }
 }
 
 fn calculate_filesystem_tree_total_sizes(current_directory: &mut Rc<Node<Dir>>) {
-    if current_directory.get_children().is_empty() {
-        return;
-    }
-
     for mut child in current_directory.get_children_mut().iter_mut() {
         calculate_filesystem_tree_total_sizes(&mut child)
     }
 
+    // a directory's size is derived from its own files and the total sizes of its child directories
+    let files_size = current_directory.value().files_size();
+    current_directory.value_mut().size = files_size;
+
     for child in current_directory.get_children().iter() {
         current_directory.value_mut().add_size(child.value().size);
     }
@@ -79,9 +156,317 @@ fn total_size_of_dirs_to_delete(current_directory: &Rc<Node<Dir>>, total_size: &
     }
 }
 
+const DISK_CAPACITY: usize = 70000000;
+
+const REQUIRED_FREE_SPACE: usize = 30000000;
+
+// Path and size of the smallest directory that frees up enough space when deleted,
+// so that the disk has required_free_space unused. Sizes must already be calculated.
+fn smallest_dir_to_delete(root_directory: &Rc<Node<Dir>>, disk_capacity: usize, required_free_space: usize) -> Option<(String, usize)> {
+    let unused_space = disk_capacity.saturating_sub(root_directory.value().size);
+
+    let space_to_free = required_free_space.saturating_sub(unused_space);
+
+    let mut smallest_dir: Option<Rc<Node<Dir>>> = None;
+
+    find_smallest_dir_to_delete(root_directory, space_to_free, &mut smallest_dir);
+
+    smallest_dir.map(|directory| (dir_path(&directory), directory.value().size))
+}
+
+fn find_smallest_dir_to_delete(current_directory: &Rc<Node<Dir>>, space_to_free: usize, smallest_dir: &mut Option<Rc<Node<Dir>>>) {
+    let size = current_directory.value().size;
+
+    // a directory smaller than the space to free can't have a big enough directory inside it
+    if size < space_to_free {
+        return;
+    }
+
+    let is_smaller = match smallest_dir {
+        Some(smallest_dir) => size < smallest_dir.value().size,
+        None => true,
+    };
+
+    if is_smaller {
+        *smallest_dir = Some(Rc::clone(current_directory));
+    }
+
+    for child in current_directory.get_children().iter() {
+        find_smallest_dir_to_delete(child, space_to_free, smallest_dir);
+    }
+}
+
+// Absolute path of a directory, found by walking up to the root: /a/e
+fn dir_path(directory: &Rc<Node<Dir>>) -> String {
+    let mut names = vec![directory.value().name.clone()];
+
+    let mut parent = directory.get_parent().upgrade();
+
+    while let Some(directory) = parent {
+        names.push(directory.value().name.clone());
+        parent = directory.get_parent().upgrade();
+    }
+
+    names.reverse();
+
+    if names[0] == "/" {
+        return format!("/{}", names[1..].join("/"));
+    }
+
+    names.join("/")
+}
+
+enum Render {
+    Tree,
+    Du(DuOptions),
+}
+
+#[derive(Clone, Copy)]
+enum DuSort {
+    Name,
+    // largest directories first
+    Size,
+}
+
+#[derive(Clone, Copy)]
+struct DuOptions {
+    sort: DuSort,
+    // / is at depth 0
+    max_depth: Option<usize>,
+    // files as well as directories, like du -a
+    all: bool,
+}
+
+// Puzzle style rendering, with the directories and files of every directory sorted by name:
+// - / (dir)
+//   - a (dir)
+//     - f (file, size=29116)
+fn render_tree(root_directory: &Rc<Node<Dir>>) -> String {
+    let mut lines = vec![];
+
+    render_tree_lines(root_directory, 0, &mut lines);
+
+    lines.join("\n")
+}
+
+fn render_tree_lines(directory: &Rc<Node<Dir>>, depth: usize, lines: &mut Vec<String>) {
+    enum Entry {
+        Dir(Rc<Node<Dir>>),
+        File(String, usize),
+    }
+
+    let indent = "  ".repeat(depth);
+
+    lines.push(format!("{}- {} (dir)", indent, directory.value().name));
+
+    let mut entries: Vec<(String, Entry)> = directory.get_children().iter().map(|child| (child.value().name.clone(), Entry::Dir(Rc::clone(child)))).collect();
+
+    entries.extend(directory.value().files.iter().map(|file| (file.name.clone(), Entry::File(file.name.clone(), file.size))));
+
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    for (_, entry) in entries {
+        match entry {
+            Entry::Dir(child) => render_tree_lines(&child, depth + 1, lines),
+            Entry::File(name, size) => lines.push(format!("{}  - {} (file, size={})", indent, name, size)),
+        }
+    }
+}
+
+// du -h style rendering: every directory's size and path, sub directories and files before the directory that contains them
+// 24M     /d
+// 48M     /
+fn render_du(root_directory: &Rc<Node<Dir>>, du_options: DuOptions) -> String {
+    let mut lines = vec![];
+
+    render_du_lines(root_directory, 0, du_options, &mut lines);
+
+    lines.join("\n")
+}
+
+fn render_du_lines(directory: &Rc<Node<Dir>>, depth: usize, du_options: DuOptions, lines: &mut Vec<String>) {
+    if let Some(max_depth) = du_options.max_depth {
+        if depth > max_depth {
+            return
+        }
+    }
+
+    let mut children: Vec<Rc<Node<Dir>>> = directory.get_children().iter().map(Rc::clone).collect();
+
+    match du_options.sort {
+        DuSort::Name => children.sort_by(|a, b| a.value().name.cmp(&b.value().name)),
+        DuSort::Size => children.sort_by_key(|child| Reverse(child.value().size)),
+    }
+
+    for child in children.iter() {
+        render_du_lines(child, depth + 1, du_options, lines);
+    }
+
+    let files_within_depth = match du_options.max_depth {
+        Some(max_depth) => depth < max_depth,
+        None => true,
+    };
+
+    if du_options.all && files_within_depth {
+        let mut files: Vec<(String, usize)> = directory.value().files.iter().map(|file| (file.path(), file.size)).collect();
+
+        match du_options.sort {
+            DuSort::Name => files.sort(),
+            DuSort::Size => files.sort_by_key(|(_, size)| Reverse(*size)),
+        }
+
+        for (path, size) in files {
+            lines.push(format!("{}\t{}", human_readable_size(size), path));
+        }
+    }
+
+    lines.push(format!("{}\t{}", human_readable_size(directory.value().size), dir_path(directory)));
+}
+
+// Sizes in powers of 1024 rounded up like du -h: 584, 93K, 8.1M, 47M
+fn human_readable_size(size: usize) -> String {
+    let units = ["", "K", "M", "G", "T", "P"];
+
+    let mut unit = 0;
+    let mut value = size as f64;
+
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        return size.to_string();
+    }
+
+    // one decimal below 10, rounding up can carry over into the next unit
+    let rounded = if value < 10.0 { (value * 10.0).ceil() / 10.0 } else { value.ceil() };
+
+    if rounded >= 1024.0 && unit < units.len() - 1 {
+        return format!("1.0{}", units[unit + 1]);
+    }
+
+    if rounded < 10.0 {
+        return format!("{:.1}{}", rounded, units[unit]);
+    }
+
+    format!("{}{}", rounded, units[unit])
+}
+
+// Looks up a directory by an absolute path (/a/e) or a path relative to from_directory (e, ../d, a/e/../../d).
+// .. from the root stays at the root, like it does in a shell.
+fn find_dir(from_directory: &Rc<Node<Dir>>, path: &str) -> Option<Rc<Node<Dir>>> {
+    let mut directory = if path.starts_with('/') { root_dir(from_directory) } else { Rc::clone(from_directory) };
+
+    for name in path.split('/') {
+        directory = match name {
+            "" | "." => directory,
+            ".." => directory.get_parent().upgrade().unwrap_or(directory),
+            name => directory.get_child(Dir::new(name.to_string()))?.upgrade()?,
+        };
+    }
+
+    Some(directory)
+}
+
+fn root_dir(directory: &Rc<Node<Dir>>) -> Rc<Node<Dir>> {
+    let mut root_directory = Rc::clone(directory);
+
+    while let Some(parent) = root_directory.get_parent().upgrade() {
+        root_directory = parent;
+    }
+
+    root_directory
+}
+
+// One line of ls output
+enum ListingEntry<'a> {
+    Dir(&'a str),
+    // name and size
+    File(&'a str, usize),
+}
+
+// dir <name> or <size> <name>, directory and file names can have spaces in them
+fn parse_listing_entry(output: &str) -> Result<ListingEntry<'_>, String> {
+    let (first, name) = match output.trim().split_once(' ') {
+        Some((first, name)) => (first, name.trim()),
+        None if output.trim() == "dir" => return Err("must provide a name to dir command!".to_string()),
+        None => return Err(format!("file must have a size and a name: {}", output)),
+    };
+
+    if first == "dir" {
+        return Ok(ListingEntry::Dir(name));
+    }
+
+    match first.parse::<usize>() {
+        Ok(size) => Ok(ListingEntry::File(name, size)),
+        Err(_) => Err(format!("file size is not a number: {}", output)),
+    }
+}
+
+// normalised "dir <name>" and "<size> <name>" entries of an ls listing, sorted so listings can be compared
+fn listing_entries(listing: &[&str]) -> Vec<String> {
+    let mut entries: Vec<String> = listing.iter().filter_map(|output| parse_listing_entry(output).ok()).map(|entry| {
+        match entry {
+            ListingEntry::Dir(name) => format!("dir {}", name),
+            ListingEntry::File(name, size) => format!("{} {}", size, name),
+        }
+    }).collect();
+
+    entries.sort();
+    entries.dedup();
+    entries
+}
+
+// the entries already recorded for a directory, in the same form as listing_entries
+fn directory_entries(directory: &Rc<Node<Dir>>) -> Vec<String> {
+    let mut entries: Vec<String> = directory.get_children().iter().map(|child| format!("dir {}", child.value().name)).collect();
+
+    entries.extend(directory.value().files.iter().map(|file| format!("{} {}", file.size, file.name)));
+
+    entries.sort();
+    entries
+}
+
+// a directory that was listed before must be listed with exactly the same entries again
+fn listing_disagrees(directory: &Rc<Node<Dir>>, listing: &[&str]) -> bool {
+    directory.value().listed && directory_entries(directory) != listing_entries(listing)
+}
+
 struct Dir {
     name: String,
+    // total size of everything in the directory, derived by calculate_filesystem_tree_total_sizes
     size: usize,
+    files: Vec<File>,
+    // set once the directory has been listed with ls
+    listed: bool,
+}
+
+struct File {
+    name: String,
+    size: usize,
+    parent: Weak<Node<Dir>>,
+}
+
+impl File {
+    fn new(name: String, size: usize, parent: &Rc<Node<Dir>>) -> File {
+        File {
+            name,
+            size,
+            parent: Rc::downgrade(parent),
+        }
+    }
+
+    // Absolute path of the file through the directory it was listed in: /a/e/i
+    fn path(&self) -> String {
+        match self.parent.upgrade() {
+            Some(parent) => match dir_path(&parent).as_str() {
+                "/" => format!("/{}", self.name),
+                parent_path => format!("{}/{}", parent_path, self.name),
+            },
+            None => self.name.clone(),
+        }
+    }
 }
 
 impl PartialEq for Dir {
@@ -94,13 +479,28 @@ impl Dir {
     fn new(name: String) -> Dir {
         Dir {
             name,
-            size: 0
+            size: 0,
+            files: vec![],
+            listed: false,
         }
     }
 
     fn add_size(&mut self, size: usize) {
         self.size += size;
     }
+
+    // files are merged by name, a file that is listed again replaces the one recorded before
+    fn add_file(&mut self, file: File) {
+        match self.files.iter_mut().find(|recorded| recorded.name == file.name) {
+            Some(recorded) => *recorded = file,
+            None => self.files.push(file),
+        }
+    }
+
+    // size of the files directly in this directory
+    fn files_size(&self) -> usize {
+        self.files.iter().map(|file| file.size).sum()
+    }
 }
 
 enum Command {
@@ -117,16 +517,14 @@ impl Command {
         if let Some(command) = tokens.next() {
             match command {
                 "cd" => {
-                    let name = tokens.next();
+                    // the whole rest of the line is the path, directory names can have spaces in them
+                    let path = current_line.trim().trim_start_matches('$').trim_start().strip_prefix("cd").unwrap_or_default().trim();
 
-                    if let None = name {
+                    if path.is_empty() {
                         panic!("no directory for cd provided")
                     }
 
-                    return Command::CD(Dir {
-                        name: name.unwrap().to_string(),
-                        size: 0,
-                    });
+                    return Command::CD(Dir::new(path.to_string()));
                 }
                 "ls" => return Command::LS,
                 _ => panic!("Command not found"),
@@ -138,85 +536,56 @@ impl Command {
 
     fn execute(&self, terminal_output: &mut Peekable<std::str::Lines>, current_directory: &mut Option<Rc<Node<Dir>>>) {
         match self {
-            Command::CD(dir) => {                
-                match dir.name.as_str() {
-                    ".." => move_up(current_directory),
-                    "/" => move_to_root(current_directory),
-                    target_dir => move_to(target_dir, current_directory),
+            Command::CD(dir) => {
+                // cd / on an empty tree creates the root directory
+                if dir.name.starts_with('/') && current_directory.is_none() {
+                    *current_directory = Some(Node::new(Dir::new("/".to_string())));
                 }
 
-                fn move_up(current_directory: &mut Option<Rc<Node<Dir>>>) {
-                    if let Some(directory) = current_directory {
-                        if let Some(parent) = directory.get_parent().upgrade() {
-                            *current_directory = Some(parent);
-                        }
+                // the target can be any path, like /a/e, .. or a/b/../c
+                if let Some(directory) = current_directory {
+                    match find_dir(directory, &dir.name) {
+                        Some(target_dir) => *current_directory = Some(target_dir),
+                        None => println!("cannot cd into directory: {} from {}. this directory has not been listed and does not exist.", dir.name, dir_path(directory)),
                     }
                 }
+            }
+            Command::LS => {
+                // every listed directory becomes a child directory, every listed file is recorded in the current directory
+                // listings are merged by name so listing the same directory again does not count anything twice
+                let listing = Self::read(terminal_output);
 
-                fn move_to_root(current_directory: &mut Option<Rc<Node<Dir>>>) {
-                    // empty tree
-                    if let None = current_directory {
-                        return *current_directory = Some(Node::new(Dir::new("/".to_string())))
+                if let Some(directory) = current_directory {
+                    if listing_disagrees(directory, &listing) {
+                        println!("warning: listing of directory {} disagrees with an earlier listing", dir_path(directory));
                     }
 
-                    while let Some(directory) = current_directory {
-                        // reached root
-                        if let None = directory.get_parent().upgrade() {
-                            return
-                        }
-
-                        move_up(current_directory);
-                    }
+                    directory.value_mut().listed = true;
                 }
 
-                fn move_to(target_dir_name: &str, current_directory: &mut Option<Rc<Node<Dir>>>) {
-                    if let Some(directory) = current_directory {
-                        let target_dir = Dir::new(target_dir_name.to_string());
-
-                        if let Some(directory) = directory.get_child(target_dir) {
-                            if let Some(directory) = directory.upgrade() {
-                                return *current_directory = Some(Rc::clone(&directory));
-                            }
-                        }
-
-                        println!("cannot cd into directory: {}. this directory has not been listed and does not exist.", target_dir_name);
-                    }
-                }
-            }
-            Command::LS => {
-                // TODO: for any file, add up the size of the file to the current directory
-                Self::read(terminal_output).iter().for_each(|output| {
-                    if output.starts_with("dir") {
-                        add_child_dir(output, current_directory);
-                    } else {
-                        add_size(output, current_directory);
+                listing.iter().for_each(|output| {
+                    match parse_listing_entry(output) {
+                        Ok(ListingEntry::Dir(name)) => add_child_dir(name, current_directory),
+                        Ok(ListingEntry::File(name, size)) => add_file(name, size, current_directory),
+                        Err(e) => println!("{}", e),
                     }
                 });
 
-                fn add_child_dir(output: &str, current_directory: &mut Option<Rc<Node<Dir>>>) {
-                    let output: Vec<&str> = output.split_whitespace().collect();
-                    let new_dir_name = output.get(1);
-
-                    if let Some(new_dir_name) = new_dir_name {
-                        if let Some(directory) = current_directory {
-                            let new_dir = Node::new(Dir::new(new_dir_name.to_string()));
-                            return directory.add_child(&directory, new_dir);
+                fn add_child_dir(new_dir_name: &str, current_directory: &mut Option<Rc<Node<Dir>>>) {
+                    if let Some(directory) = current_directory {
+                        if directory.get_child(Dir::new(new_dir_name.to_string())).is_some() {
+                            return
                         }
-                    } else {
-                        println!("must provide a name to dir command!");
+
+                        let new_dir = Node::new(Dir::new(new_dir_name.to_string()));
+                        directory.add_child(&directory, new_dir);
                     }
                 }
 
-                fn add_size(output: &str, current_directory: &mut Option<Rc<Node<Dir>>>) {
-                    let output: Vec<&str> = output.split_whitespace().collect();
-                    let file_size = output.get(0);
-
-                    if let Some(file_size) = file_size {
-                        let file_size = file_size.parse::<usize>().unwrap();
-
-                        if let Some(directory) = current_directory {
-                            directory.value_mut().add_size(file_size);
-                        }
+                fn add_file(file_name: &str, file_size: usize, current_directory: &mut Option<Rc<Node<Dir>>>) {
+                    if let Some(directory) = current_directory {
+                        let file = File::new(file_name.to_string(), file_size, directory);
+                        directory.value_mut().add_file(file);
                     }
                 }
             }
@@ -495,8 +864,10 @@ mod tests {
 
         ls.execute(&mut terminal_output, &mut current_directory);
 
+        // b is listed twice, the later size replaces the earlier one
         let current_directory = current_directory.unwrap();
-        assert_eq!(current_directory.value().size, 16);
+        assert_eq!(current_directory.value().files_size(), 11);
+        assert_eq!(current_directory.value().files.len(), 2);
     }
 
     #[test]
@@ -517,7 +888,7 @@ mod tests {
         ls.execute(&mut terminal_output, &mut current_directory);
 
         let current_directory = current_directory.unwrap();
-        assert_eq!(current_directory.value().size, 21);
+        assert_eq!(current_directory.value().files_size(), 21);
     }
 
     #[test]
@@ -535,22 +906,22 @@ mod tests {
         ls.execute(&mut terminal_output, &mut current_directory);
 
         let current_directory = current_directory.unwrap();
-        assert_eq!(current_directory.value().size, 0);
+        assert_eq!(current_directory.value().files_size(), 0);
     }
 
     #[test]
     fn tree_total_sizes() {
         let mut a = Node::new(Dir::new("/".to_string()));
 
-        a.value_mut().size = 1;
+        a.value_mut().add_file(File::new("a.txt".to_string(), 1, &a));
 
         let b = Node::new(Dir::new("b".to_string()));
         
-        b.value_mut().size = 1;
+        b.value_mut().add_file(File::new("b.txt".to_string(), 1, &b));
 
         let c = Node::new(Dir::new("c".to_string()));
 
-        c.value_mut().size = 1;
+        c.value_mut().add_file(File::new("c.txt".to_string(), 1, &c));
 
         b.add_child(&b, c);
 
@@ -565,23 +936,23 @@ mod tests {
     fn tree_total_sizes_2_level() {
         let mut a = Node::new(Dir::new("/".to_string()));
 
-        a.value_mut().size = 1;
+        a.value_mut().add_file(File::new("a.txt".to_string(), 1, &a));
 
         let b = Node::new(Dir::new("b".to_string()));
         
-        b.value_mut().size = 1;
+        b.value_mut().add_file(File::new("b.txt".to_string(), 1, &b));
 
         let c = Node::new(Dir::new("c".to_string()));
 
-        c.value_mut().size = 1;
+        c.value_mut().add_file(File::new("c.txt".to_string(), 1, &c));
 
         let observer_c = Rc::clone(&c);
 
         let d = Node::new(Dir::new("d".to_string()));
         let e = Node::new(Dir::new("d".to_string()));
 
-        d.value_mut().size = 20;
-        e.value_mut().size = 21;
+        d.value_mut().add_file(File::new("d.txt".to_string(), 20, &d));
+        e.value_mut().add_file(File::new("e.txt".to_string(), 21, &e));
 
         c.add_child(&c, d);
         c.add_child(&c, e);
@@ -599,27 +970,27 @@ mod tests {
     fn dirs_to_delete() {
         let mut root = Node::new(Dir::new("/".to_string()));
 
-        let _b = root.value_mut().add_size(14848514);
-        let _c = root.value_mut().add_size(8504156);
+        root.value_mut().add_file(File::new("b".to_string(), 14848514, &root));
+        root.value_mut().add_file(File::new("c".to_string(), 8504156, &root));
 
         let a = Node::new(Dir::new("a".to_string()));
 
-        let _f = a.value_mut().add_size(29116);
-        let _g = a.value_mut().add_size(2557);
-        let _h = a.value_mut().add_size(62596);
+        a.value_mut().add_file(File::new("f".to_string(), 29116, &a));
+        a.value_mut().add_file(File::new("g".to_string(), 2557, &a));
+        a.value_mut().add_file(File::new("h".to_string(), 62596, &a));
 
         let e = Node::new(Dir::new("e".to_string()));
 
-        let _i = e.value_mut().add_size(584);
+        e.value_mut().add_file(File::new("i".to_string(), 584, &e));
 
         a.add_child(&a, e);
 
         let d = Node::new(Dir::new("d".to_string()));
 
-        let _j = d.value_mut().add_size(4060174);
-        let _d_log = d.value_mut().add_size(8033020);
-        let _d_ext = d.value_mut().add_size(5626152);
-        let _k = d.value_mut().add_size(7214296);
+        d.value_mut().add_file(File::new("j".to_string(), 4060174, &d));
+        d.value_mut().add_file(File::new("d.log".to_string(), 8033020, &d));
+        d.value_mut().add_file(File::new("d.ext".to_string(), 5626152, &d));
+        d.value_mut().add_file(File::new("k".to_string(), 7214296, &d));
 
         root.add_child(&root, a);
         root.add_child(&root, d);
@@ -632,4 +1003,401 @@ mod tests {
 
         assert_eq!(total_size, 95437);
     }
+
+    const EXAMPLE_TERMINAL_OUTPUT: &str = "$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k";
+
+    fn example_filesystem() -> Rc<Node<Dir>> {
+        let mut terminal_output = EXAMPLE_TERMINAL_OUTPUT.lines().peekable();
+
+        let mut root = CommandExecutor::build_filesystem(&mut terminal_output).unwrap();
+
+        calculate_filesystem_tree_total_sizes(&mut root);
+
+        root
+    }
+
+    #[test]
+    fn smallest_dir_to_delete_example() {
+        let root = example_filesystem();
+
+        assert_eq!(root.value().size, 48381165);
+
+        assert_eq!(smallest_dir_to_delete(&root, DISK_CAPACITY, REQUIRED_FREE_SPACE), Some(("/d".to_string(), 24933642)));
+    }
+
+    #[test]
+    fn smallest_dir_to_delete_other_sizes() {
+        let root = example_filesystem();
+
+        // disk is full and needs 90000 freed, e is too small so a is the smallest
+        assert_eq!(smallest_dir_to_delete(&root, 48381165, 90000), Some(("/a".to_string(), 94853)));
+
+        // already enough space, any directory frees enough
+        assert_eq!(smallest_dir_to_delete(&root, 100000000, 100), Some(("/a/e".to_string(), 584)));
+
+        // more than the whole disk, even the root isn't enough
+        assert_eq!(smallest_dir_to_delete(&root, DISK_CAPACITY, 80000000), None);
+    }
+
+    #[test]
+    fn ls_records_files() {
+        let ls = Command::new("$ ls");
+
+        let mut terminal_output = "dir a\n14848514 b.txt\n8504156 c dat".lines().peekable();
+
+        let root = Node::new(Dir::new("/".to_string()));
+
+        let mut current_directory = Some(Rc::clone(&root));
+
+        ls.execute(&mut terminal_output, &mut current_directory);
+
+        let directory = root.value();
+        let files: Vec<(&str, usize)> = directory.files.iter().map(|file| (file.name.as_str(), file.size)).collect();
+
+        assert_eq!(files, vec![("b.txt", 14848514), ("c dat", 8504156)]);
+        assert!(directory.files.iter().all(|file| Rc::ptr_eq(&file.parent.upgrade().unwrap(), &root)));
+        assert!(root.get_child(Dir::new("a".to_string())).is_some());
+
+        // size is only derived once the tree is calculated
+        assert_eq!(directory.size, 0);
+    }
+
+    #[test]
+    fn ls_skips_invalid_files() {
+        let ls = Command::new("$ ls");
+
+        let mut terminal_output = "abc b.txt\n12".lines().peekable();
+
+        let root = Node::new(Dir::new("/".to_string()));
+
+        let mut current_directory = Some(Rc::clone(&root));
+
+        ls.execute(&mut terminal_output, &mut current_directory);
+
+        assert!(root.value().files.is_empty());
+    }
+
+    #[test]
+    fn example_sizes_derived_from_files() {
+        let root = example_filesystem();
+
+        let a = root.get_child(Dir::new("a".to_string())).unwrap().upgrade().unwrap();
+        let e = a.get_child(Dir::new("e".to_string())).unwrap().upgrade().unwrap();
+
+        assert_eq!(e.value().size, 584);
+        assert_eq!(a.value().files_size(), 94269);
+        assert_eq!(a.value().size, 94853);
+        assert_eq!(root.value().files.len(), 2);
+    }
+
+    #[test]
+    fn ls_twice_is_idempotent() {
+        let ls = Command::new("$ ls");
+
+        let mut terminal_output = "dir a\n100 b.txt\n200 c.dat".lines().peekable();
+        let mut terminal_output_2 = "200 c.dat\ndir a\n100 b.txt".lines().peekable();
+
+        let mut root = Node::new(Dir::new("/".to_string()));
+
+        let mut current_directory = Some(Rc::clone(&root));
+
+        ls.execute(&mut terminal_output, &mut current_directory);
+        assert!(!listing_disagrees(&root, &["100 b.txt", "dir a", "200 c.dat"]));
+
+        ls.execute(&mut terminal_output_2, &mut current_directory);
+
+        calculate_filesystem_tree_total_sizes(&mut root);
+
+        assert_eq!(root.get_children().len(), 1);
+        assert_eq!(root.value().files.len(), 2);
+        assert_eq!(root.value().size, 300);
+    }
+
+    #[test]
+    fn ls_overlapping_listings_are_merged() {
+        let ls = Command::new("$ ls");
+
+        let mut terminal_output = "dir a\n100 b.txt".lines().peekable();
+        let mut terminal_output_2 = "dir a\ndir d\n150 b.txt\n200 c.dat".lines().peekable();
+
+        let mut root = Node::new(Dir::new("/".to_string()));
+
+        let mut current_directory = Some(Rc::clone(&root));
+
+        ls.execute(&mut terminal_output, &mut current_directory);
+        assert!(listing_disagrees(&root, &["dir a", "dir d", "150 b.txt", "200 c.dat"]));
+
+        ls.execute(&mut terminal_output_2, &mut current_directory);
+
+        calculate_filesystem_tree_total_sizes(&mut root);
+
+        // the latest size of b.txt wins
+        assert_eq!(root.get_children().len(), 2);
+        assert_eq!(root.value().files.len(), 2);
+        assert_eq!(root.value().size, 350);
+    }
+
+    #[test]
+    fn unlisted_directory_never_disagrees() {
+        let root = Node::new(Dir::new("/".to_string()));
+
+        assert!(!listing_disagrees(&root, &["dir a", "100 b.txt"]));
+    }
+
+    #[test]
+    fn example_listed_twice() {
+        let terminal_output = format!("{}\n$ cd /\n$ ls\n{}", EXAMPLE_TERMINAL_OUTPUT, "dir a\n14848514 b.txt\n8504156 c.dat\ndir d");
+        let mut root = CommandExecutor::build_filesystem(&mut terminal_output.lines().peekable()).unwrap();
+
+        calculate_filesystem_tree_total_sizes(&mut root);
+
+        assert_eq!(root.value().size, 48381165);
+        assert_eq!(root.get_children().len(), 2);
+    }
+
+    #[test]
+    fn example_tree() {
+        let root = example_filesystem();
+
+        let expected = "- / (dir)
+  - a (dir)
+    - e (dir)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)";
+
+        assert_eq!(render_tree(&root), expected);
+    }
+
+    #[test]
+    fn example_du() {
+        let root = example_filesystem();
+
+        let du_options = DuOptions { sort: DuSort::Name, max_depth: None, all: false };
+        assert_eq!(render_du(&root, du_options), "584\t/a/e\n93K\t/a\n24M\t/d\n47M\t/");
+
+        let du_options = DuOptions { sort: DuSort::Size, max_depth: None, all: false };
+        assert_eq!(render_du(&root, du_options), "24M\t/d\n584\t/a/e\n93K\t/a\n47M\t/");
+
+        let du_options = DuOptions { sort: DuSort::Name, max_depth: Some(1), all: false };
+        assert_eq!(render_du(&root, du_options), "93K\t/a\n24M\t/d\n47M\t/");
+
+        let du_options = DuOptions { sort: DuSort::Name, max_depth: Some(0), all: false };
+        assert_eq!(render_du(&root, du_options), "47M\t/");
+    }
+
+    #[test]
+    fn example_du_all() {
+        let root = example_filesystem();
+
+        let du_options = DuOptions { sort: DuSort::Name, max_depth: Some(1), all: true };
+        assert_eq!(render_du(&root, du_options), "93K\t/a\n24M\t/d\n15M\t/b.txt\n8.2M\t/c.dat\n47M\t/");
+
+        let du_options = DuOptions { sort: DuSort::Size, max_depth: None, all: true };
+        let du = render_du(&root, du_options);
+        let lines: Vec<&str> = du.lines().collect();
+
+        assert_eq!(lines.len(), 14);
+        assert_eq!(lines[0..7], ["7.7M\t/d/d.log", "6.9M\t/d/k", "5.4M\t/d/d.ext", "3.9M\t/d/j", "24M\t/d", "584\t/a/e/i", "584\t/a/e"]);
+        assert_eq!(lines[7..11], ["62K\t/a/h.lst", "29K\t/a/f", "2.5K\t/a/g", "93K\t/a"]);
+    }
+
+    #[test]
+    fn human_readable_sizes() {
+        assert_eq!(human_readable_size(0), "0");
+        assert_eq!(human_readable_size(1023), "1023");
+        assert_eq!(human_readable_size(1024), "1.0K");
+        assert_eq!(human_readable_size(1025), "1.1K");
+        assert_eq!(human_readable_size(94853), "93K");
+        assert_eq!(human_readable_size(10 * 1024 * 1024 - 1), "10M");
+        assert_eq!(human_readable_size(1024 * 1024 - 1), "1.0M");
+        assert_eq!(human_readable_size(48381165), "47M");
+    }
+
+    #[test]
+    fn find_dir_by_path() {
+        let root = example_filesystem();
+
+        let a = find_dir(&root, "a").unwrap();
+        let e = find_dir(&root, "/a/e").unwrap();
+
+        assert_eq!(dir_path(&find_dir(&root, "/").unwrap()), "/");
+        assert_eq!(dir_path(&find_dir(&e, "/").unwrap()), "/");
+        assert_eq!(dir_path(&find_dir(&e, "..").unwrap()), "/a");
+        assert_eq!(dir_path(&find_dir(&e, "../..").unwrap()), "/");
+        assert_eq!(dir_path(&find_dir(&e, "../../d").unwrap()), "/d");
+        assert_eq!(dir_path(&find_dir(&a, "e/../../d/").unwrap()), "/d");
+        assert_eq!(dir_path(&find_dir(&a, "./e").unwrap()), "/a/e");
+        assert_eq!(dir_path(&find_dir(&root, "../../a").unwrap()), "/a");
+        assert!(Rc::ptr_eq(&find_dir(&e, "/a/e").unwrap(), &e));
+
+        assert!(find_dir(&root, "e").is_none());
+        assert!(find_dir(&a, "/e").is_none());
+        assert!(find_dir(&root, "a/e/i").is_none());
+    }
+
+    #[test]
+    fn cd_multi_segment_path() {
+        let root = example_filesystem();
+
+        let mut current_directory = Some(Rc::clone(&root));
+
+        Command::new("$ cd a/e/../../d").execute(&mut "".lines().peekable(), &mut current_directory);
+        assert_eq!(dir_path(current_directory.as_ref().unwrap()), "/d");
+
+        Command::new("$ cd /a/e").execute(&mut "".lines().peekable(), &mut current_directory);
+        assert_eq!(dir_path(current_directory.as_ref().unwrap()), "/a/e");
+
+        // a path that does not exist leaves the current directory alone
+        Command::new("$ cd ../d").execute(&mut "".lines().peekable(), &mut current_directory);
+        assert_eq!(dir_path(current_directory.as_ref().unwrap()), "/a/e");
+    }
+
+    #[test]
+    fn same_name_dirs_have_different_paths() {
+        let terminal_output = "$ cd /
+$ ls
+dir a
+dir b
+$ cd a
+$ ls
+dir a
+10 x
+$ cd a
+$ ls
+20 x
+$ cd /b
+$ ls
+dir a
+$ cd a
+$ ls
+30 x";
+
+        let mut root = CommandExecutor::build_filesystem(&mut terminal_output.lines().peekable()).unwrap();
+
+        calculate_filesystem_tree_total_sizes(&mut root);
+
+        let a = find_dir(&root, "/a/a").unwrap();
+        let b = find_dir(&root, "/b/a").unwrap();
+
+        assert_eq!(dir_path(&a), "/a/a");
+        assert_eq!(dir_path(&b), "/b/a");
+        assert_eq!(a.value().size, 20);
+        assert_eq!(b.value().size, 30);
+        assert_eq!(find_dir(&root, "a").unwrap().value().size, 30);
+
+        let file_paths: Vec<String> = a.value().files.iter().map(|file| file.path()).collect();
+        assert_eq!(file_paths, vec!["/a/a/x"]);
+    }
+
+    #[test]
+    fn example_file_paths() {
+        let root = example_filesystem();
+
+        let file_paths: Vec<String> = root.value().files.iter().map(|file| file.path()).collect();
+        assert_eq!(file_paths, vec!["/b.txt", "/c.dat"]);
+
+        let e = find_dir(&root, "/a/e").unwrap();
+        assert_eq!(e.value().files[0].path(), "/a/e/i");
+    }
+
+    #[test]
+    fn ls_names_with_spaces() {
+        let ls = Command::new("$ ls");
+
+        let mut terminal_output = "dir my docs\n100 my notes.txt".lines().peekable();
+
+        let root = Node::new(Dir::new("/".to_string()));
+
+        let mut current_directory = Some(Rc::clone(&root));
+
+        ls.execute(&mut terminal_output, &mut current_directory);
+
+        assert!(root.get_child(Dir::new("my docs".to_string())).is_some());
+        assert!(root.get_child(Dir::new("my".to_string())).is_none());
+        assert_eq!(root.value().files[0].name, "my notes.txt");
+    }
+
+    #[test]
+    fn parse_listing_entries() {
+        assert!(matches!(parse_listing_entry("dir my docs"), Ok(ListingEntry::Dir("my docs"))));
+        assert!(matches!(parse_listing_entry("584 i"), Ok(ListingEntry::File("i", 584))));
+        assert!(parse_listing_entry("dir").is_err());
+        assert!(parse_listing_entry("584").is_err());
+        assert!(parse_listing_entry("abc i").is_err());
+    }
+
+    #[test]
+    fn ls_twice_with_spaces_in_names_agrees() {
+        let ls = Command::new("$ ls");
+
+        let listing = "dir my docs\n100 my notes.txt";
+
+        let root = Node::new(Dir::new("/".to_string()));
+
+        let mut current_directory = Some(Rc::clone(&root));
+
+        ls.execute(&mut listing.lines().peekable(), &mut current_directory);
+
+        assert!(!listing_disagrees(&root, &listing.lines().collect::<Vec<&str>>()));
+
+        ls.execute(&mut listing.lines().peekable(), &mut current_directory);
+
+        assert_eq!(root.get_children().len(), 1);
+        assert_eq!(root.value().files.len(), 1);
+    }
+
+    #[test]
+    fn cd_into_dir_with_spaces() {
+        let terminal_output = "$ cd /
+$ ls
+dir my dir
+$ cd my dir
+$ ls
+100 x
+$ cd ..
+$ cd  my dir/";
+
+        let root = CommandExecutor::build_filesystem(&mut terminal_output.lines().peekable()).unwrap();
+
+        let my_dir = find_dir(&root, "/my dir").unwrap();
+
+        assert!(root.value().files.is_empty());
+        assert_eq!(my_dir.value().files[0].path(), "/my dir/x");
+
+        let mut current_directory = Some(Rc::clone(&root));
+
+        Command::new("$ cd  my dir/").execute(&mut "".lines().peekable(), &mut current_directory);
+        assert!(Rc::ptr_eq(current_directory.as_ref().unwrap(), &my_dir));
+    }
 }
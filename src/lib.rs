@@ -0,0 +1,46 @@
+// Helpers shared between the days
+
+// xorshift pseudo random numbers, the same seed always gives the same numbers.
+// Used for generated inputs in tests and benches, so every run checks the same cases.
+pub struct XorShift(u64);
+
+impl XorShift {
+    // The seed must not be 0, or every number is 0
+    pub fn new(seed: u64) -> XorShift {
+        XorShift(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // A number from 0 up to, but not including, below
+    pub fn below(&mut self, below: usize) -> usize {
+        (self.next_u64() % below as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let mut a = XorShift::new(0x2022);
+        let mut b = XorShift::new(0x2022);
+
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn below_stays_in_range() {
+        let mut random = XorShift::new(0x2022);
+
+        assert!((0..1000).all(|_| random.below(7) < 7));
+    }
+}